@@ -0,0 +1,113 @@
+use bracket_lib::prelude::Point;
+
+use legion::Entity;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{components::Player, simulation::save::entity_option, types::Difficulty};
+
+/// The number of seconds before a computer player sends its first attack wave
+const FIRST_WAVE: f32 = 180.0;
+/// The number of seconds between later attack waves
+const WAVE_INTERVAL: f32 = 90.0;
+
+/// What a computer player remembers between its decisions
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Brain {
+    player: Player,
+    difficulty: Difficulty,
+    think: f32,
+    wave: f32,
+    waves: usize,
+    #[serde(with = "entity_option")]
+    scout: Option<Entity>,
+    waypoint: usize,
+    enemy_base: Option<Point>,
+}
+
+impl Brain {
+    pub fn new(player: Player, difficulty: Difficulty) -> Self {
+        Self {
+            player,
+            difficulty,
+            think: 0.0,
+            wave: FIRST_WAVE,
+            waves: 0,
+            scout: None,
+            waypoint: 0,
+            enemy_base: None,
+        }
+    }
+
+    /// Count down by the given number of seconds and return whether it is time to decide
+    pub fn tick(&mut self, dt: f32) -> bool {
+        self.wave -= dt;
+        self.think -= dt;
+        if self.think <= 0.0 {
+            self.think = self.difficulty.reaction_time();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Return whether enough time has passed to send the next attack wave
+    pub fn wave_ready(&self) -> bool {
+        self.wave <= 0.0
+    }
+    pub fn send_wave(&mut self) {
+        self.wave = WAVE_INTERVAL;
+        self.waves += 1;
+    }
+    /// Return the smallest army the next attack wave will be sent with
+    pub fn wave_size(&self) -> usize {
+        8 + 4 * self.waves
+    }
+
+    pub fn set_scout(&mut self, scout: Option<Entity>) {
+        self.scout = scout;
+    }
+    /// Move on to the next scouting waypoint
+    pub fn next_waypoint(&mut self) {
+        self.waypoint += 1;
+    }
+    pub fn set_enemy_base(&mut self, point: Option<Point>) {
+        self.enemy_base = point;
+    }
+
+    pub fn player(&self) -> Player {
+        self.player
+    }
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+    pub fn scout(&self) -> Option<Entity> {
+        self.scout
+    }
+    pub fn waypoint(&self) -> usize {
+        self.waypoint
+    }
+    pub fn enemy_base(&self) -> Option<Point> {
+        self.enemy_base
+    }
+}
+
+/// A resource holding the Brain of every computer player
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Brains {
+    brains: Vec<Brain>,
+}
+
+impl Brains {
+    pub fn new() -> Self {
+        Self { brains: Vec::new() }
+    }
+    pub fn with_brain(mut self, brain: Brain) -> Self {
+        self.brains.push(brain);
+        self
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Brain> {
+        self.brains.iter_mut()
+    }
+}
@@ -0,0 +1,554 @@
+mod brain;
+
+use bracket_lib::prelude::*;
+
+use legion::{systems::ParallelRunnable, *};
+
+pub use brain::{Brain, Brains};
+
+use crate::{
+    components::{Building, GameCell, Player, ResourceNode, Task, Unit, Worker},
+    types::{
+        BuildingKind, Map, Mode, Order, Orders, PlayerInfo, Players, ResourceKind, Time, UnitKind,
+        Vision, MAX_SUPPLY,
+    },
+};
+
+/// The number of workers a computer player keeps on gas
+const GAS_WORKERS: usize = 3;
+/// The fraction of its HP below which a unit is pulled out of a fight
+const RETREAT_HEALTH: f32 = 0.3;
+/// How far units notice enemy buildings, and how far from home enemy units are treated as an
+/// attack
+const SIGHT: i32 = 10;
+/// How far from home a computer player looks for resource nodes
+const NODE_RANGE: i32 = 15;
+
+/// What a computer player knows about a unit when deciding
+#[derive(Clone)]
+struct UnitView {
+    entity: Entity,
+    point: Point,
+    player: Player,
+    kind: UnitKind,
+    hp: i32,
+    health: f32,
+    task: Option<Task>,
+    resource: Option<ResourceKind>,
+    idle: bool,
+    moving: bool,
+    focus: Option<Entity>,
+}
+
+/// What a computer player knows about a building when deciding
+#[derive(Clone)]
+struct BuildingView {
+    entity: Entity,
+    point: Point,
+    footprint: Rect,
+    player: Player,
+    kind: BuildingKind,
+    constructed: bool,
+    queued: usize,
+}
+
+/// What a computer player knows about a resource node when deciding
+#[derive(Clone)]
+struct NodeView {
+    entity: Entity,
+    point: Point,
+    kind: ResourceKind,
+}
+
+/// Everything a computer player looks at when making a decision
+struct View {
+    units: Vec<UnitView>,
+    buildings: Vec<BuildingView>,
+    nodes: Vec<NodeView>,
+}
+
+/// The match state a computer player decides from and the orders it issues into
+struct Context<'a> {
+    map: &'a Map,
+    players: &'a Players,
+    vision: &'a Vision,
+    orders: &'a mut Orders,
+}
+
+impl View {
+    /// Return the part of the view the player's team can see through the fog: everything its
+    /// own team owns, but only the enemies in sight. The terrain and resource nodes are known
+    /// from the start, as they are to a human who knows the map.
+    fn seen_by(&self, player: Player, team: usize, players: &Players, vision: &Vision) -> View {
+        View {
+            units: self
+                .units
+                .iter()
+                .filter(|unit| {
+                    !players.hostile(player, unit.player) || vision.is_visible(team, unit.point)
+                })
+                .cloned()
+                .collect(),
+            buildings: self
+                .buildings
+                .iter()
+                .filter(|building| {
+                    !players.hostile(player, building.player)
+                        || vision.any_visible(team, building.footprint)
+                })
+                .cloned()
+                .collect(),
+            nodes: self.nodes.clone(),
+        }
+    }
+}
+
+/// Let each computer player look over the match and issue the same orders a human would for its
+/// economy, buildings, army, scouting and attack waves
+pub fn think() -> impl ParallelRunnable {
+    SystemBuilder::new("think")
+        .read_resource::<Time>()
+        .read_resource::<Map>()
+        .read_resource::<Players>()
+        .read_resource::<Vision>()
+        .write_resource::<Brains>()
+        .write_resource::<Orders>()
+        .with_query(<(Read<GameCell>, Read<Unit>, Read<Player>, TryRead<Worker>)>::query())
+        .with_query(<(Read<GameCell>, Read<Building>, Read<Player>)>::query())
+        .with_query(<(Read<GameCell>, Read<ResourceNode>)>::query())
+        .build(
+            |_,
+             world,
+             (time, map, players, vision, brains, orders),
+             (unit_query, building_query, node_query)| {
+                let mut view = View {
+                    units: Vec::new(),
+                    buildings: Vec::new(),
+                    nodes: Vec::new(),
+                };
+                for chunk in unit_query.iter_chunks(world) {
+                    for (e, (cell, unit, player, worker)) in chunk.into_iter_entities() {
+                        view.units.push(UnitView {
+                            entity: e,
+                            point: cell.point(),
+                            player: *player,
+                            kind: unit.kind(),
+                            hp: unit.hp(),
+                            health: unit.hp() as f32 / unit.max_hp() as f32,
+                            task: worker.map(|worker| worker.task()),
+                            resource: worker
+                                .and_then(|worker| worker.node())
+                                .map(|(_, _, kind)| kind),
+                            idle: !cell.has_destination() && cell.following().is_none(),
+                            moving: cell.is_moving() && cell.has_destination(),
+                            focus: unit.focus(),
+                        });
+                    }
+                }
+                for chunk in building_query.iter_chunks(world) {
+                    for (e, (cell, building, player)) in chunk.into_iter_entities() {
+                        view.buildings.push(BuildingView {
+                            entity: e,
+                            point: cell.footprint().center(),
+                            footprint: cell.footprint(),
+                            player: *player,
+                            kind: building.kind(),
+                            constructed: building.is_constructed(),
+                            queued: building.queue().len(),
+                        });
+                    }
+                }
+                for chunk in node_query.iter_chunks(world) {
+                    for (e, (cell, node)) in chunk.into_iter_entities() {
+                        view.nodes.push(NodeView {
+                            entity: e,
+                            point: cell.point(),
+                            kind: node.kind(),
+                        });
+                    }
+                }
+
+                let mut context = Context {
+                    map,
+                    players,
+                    vision,
+                    orders,
+                };
+                for brain in brains.iter_mut() {
+                    if brain.tick(time.dt()) {
+                        if let Some(info) = players.get(brain.player()) {
+                            let seen = view.seen_by(brain.player(), info.team(), players, vision);
+                            decide(brain, info, &seen, &mut context);
+                        }
+                    }
+                }
+            },
+        )
+}
+
+fn decide(brain: &mut Brain, info: &PlayerInfo, view: &View, context: &mut Context) {
+    let player = brain.player();
+    scout(brain, info.team(), view, context);
+
+    let home = view
+        .buildings
+        .iter()
+        .find(|building| building.player == player && building.kind.is_depot())
+        .map(|building| building.point);
+    if let Some(home) = home {
+        gather(view, player, home, context.orders);
+        construct(brain, view, info, home, context.map, context.orders);
+    }
+    train(brain, view, info, context.orders);
+    let defending = home.map_or(false, |home| {
+        defend(brain, view, context.players, home, context.orders)
+    });
+    if !defending {
+        attack(brain, view, context.orders);
+    }
+    // micro comes last so it overrides the broader orders for units already fighting
+    micro(brain, view, context.players, home, context.orders);
+}
+
+/// Put idle workers to work, keeping a few on gas and the rest on the minerals nearest home
+fn gather(view: &View, player: Player, home: Point, orders: &mut Orders) {
+    let mut on_gas = view
+        .units
+        .iter()
+        .filter(|unit| {
+            unit.player == player
+                && unit.task.map_or(false, |task| task != Task::Idle)
+                && unit.resource == Some(ResourceKind::Gas)
+        })
+        .count();
+    let gas = nearest_node(view, home, ResourceKind::Gas);
+    let minerals = nearest_node(view, home, ResourceKind::Minerals);
+
+    for worker in view
+        .units
+        .iter()
+        .filter(|unit| unit.player == player && unit.task == Some(Task::Idle) && unit.idle)
+    {
+        let node = if on_gas < GAS_WORKERS && gas.is_some() {
+            on_gas += 1;
+            gas
+        } else {
+            minerals
+        };
+        if let Some(node) = node {
+            orders.push(player, Order::Gather(vec![worker.entity], node));
+        }
+    }
+}
+
+/// Return the closest node of the given resource within reach of the point
+fn nearest_node(view: &View, point: Point, kind: ResourceKind) -> Option<Entity> {
+    view.nodes
+        .iter()
+        .filter(|node| node.kind == kind && tiles_apart(node.point, point) <= NODE_RANGE)
+        .min_by_key(|node| DistanceAlg::PythagorasSquared.distance2d(node.point, point) as i32)
+        .map(|node| node.entity)
+}
+
+/// Start a supply building when close to the supply cap, or otherwise another production
+/// building, one construction site at a time
+fn construct(
+    brain: &Brain,
+    view: &View,
+    info: &PlayerInfo,
+    home: Point,
+    map: &Map,
+    orders: &mut Orders,
+) {
+    let player = brain.player();
+    let difficulty = brain.difficulty();
+    if view
+        .buildings
+        .iter()
+        .any(|building| building.player == player && !building.constructed)
+    {
+        return;
+    }
+
+    let buildings = info.race().buildings();
+    let production = view
+        .buildings
+        .iter()
+        .filter(|building| building.player == player && is_production(building.kind))
+        .count();
+    let (used, cap) = info.supply();
+    let kind = if cap - used < difficulty.supply_margin() && cap < MAX_SUPPLY {
+        buildings
+            .iter()
+            .find(|kind| !kind.is_depot() && kind.supply() > 0)
+    } else if production < difficulty.max_production() {
+        buildings.iter().find(|kind| is_production(**kind))
+    } else {
+        None
+    };
+
+    if let Some(kind) = kind.copied() {
+        if info.can_afford(kind.cost()) {
+            if let Some(origin) = find_site(view, map, home, kind) {
+                orders.push(player, Order::Build(kind, origin));
+            }
+        }
+    }
+}
+
+/// Return whether the building trains army units rather than workers
+fn is_production(kind: BuildingKind) -> bool {
+    !kind.is_depot() && !kind.trains().is_empty()
+}
+
+/// Return the top left corner of the nearest spot around the point with room for the building
+/// and a one tile gap around it, so paths between buildings stay open
+fn find_site(view: &View, map: &Map, center: Point, kind: BuildingKind) -> Option<Point> {
+    let (w, h) = kind.size();
+    for r in 4..16 {
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if dx.abs() != r && dy.abs() != r {
+                    continue;
+                }
+                let origin = Point::new(center.x + dx, center.y + dy);
+                let padded = Rect::with_size(origin.x - 1, origin.y - 1, w + 2, h + 2);
+                if map.is_clear(padded)
+                    && !view
+                        .units
+                        .iter()
+                        .any(|unit| padded.point_in_rect(unit.point))
+                {
+                    return Some(origin);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Keep workers coming from each depot and army units from each production building
+fn train(brain: &Brain, view: &View, info: &PlayerInfo, orders: &mut Orders) {
+    let player = brain.player();
+    let difficulty = brain.difficulty();
+    let workers = view
+        .units
+        .iter()
+        .filter(|unit| unit.player == player && unit.kind.is_worker())
+        .count();
+    let worker = info.race().worker();
+    if workers < difficulty.max_workers() && info.can_afford(worker.cost()) {
+        let depot = view.buildings.iter().find(|building| {
+            building.player == player
+                && building.constructed
+                && building.kind.is_depot()
+                && building.queued == 0
+        });
+        if let Some(depot) = depot {
+            orders.push(player, Order::Train(depot.entity, worker));
+        }
+    }
+
+    for building in view.buildings.iter().filter(|building| {
+        building.player == player
+            && building.constructed
+            && is_production(building.kind)
+            && building.queued < difficulty.max_queued()
+    }) {
+        // the later units a building trains are the stronger ones
+        let unit = building
+            .kind
+            .trains()
+            .iter()
+            .rev()
+            .find(|unit| info.can_afford(unit.cost()) && info.has_supply(unit.supply()));
+        if let Some(unit) = unit {
+            orders.push(player, Order::Train(building.entity, *unit));
+        }
+    }
+}
+
+/// Send a scout between points around the map until an enemy building is spotted, and forget
+/// the enemy base once it is back in sight with nothing hostile left there
+fn scout(brain: &mut Brain, team: usize, view: &View, context: &mut Context) {
+    let player = brain.player();
+    let players = context.players;
+    match brain.enemy_base() {
+        Some(base) => {
+            if context.vision.is_visible(team, base)
+                && !view.buildings.iter().any(|building| {
+                    players.hostile(player, building.player)
+                        && tiles_apart(building.point, base) <= SIGHT
+                })
+            {
+                brain.set_enemy_base(None);
+            }
+        }
+        None => {
+            let spotted = view.buildings.iter().find(|building| {
+                players.hostile(player, building.player)
+                    && view.units.iter().any(|unit| {
+                        unit.player == player && tiles_apart(unit.point, building.point) <= SIGHT
+                    })
+            });
+            if let Some(building) = spotted {
+                brain.set_enemy_base(Some(building.point));
+                brain.set_scout(None);
+            }
+        }
+    }
+    if brain.enemy_base().is_some() {
+        return;
+    }
+
+    let scout = brain
+        .scout()
+        .and_then(|scout| view.units.iter().find(|unit| unit.entity == scout))
+        .or_else(|| {
+            view.units
+                .iter()
+                .find(|unit| unit.player == player && !unit.kind.is_worker() && unit.idle)
+        });
+    if let Some(scout) = scout {
+        brain.set_scout(Some(scout.entity));
+        if scout.idle {
+            let waypoints = waypoints(context.map);
+            let target = waypoints[brain.waypoint() % waypoints.len()];
+            brain.next_waypoint();
+            context
+                .orders
+                .push(player, Order::Move(vec![scout.entity], target, Mode::Move));
+        }
+    }
+}
+
+/// Return the points a scout visits in turn: the middle of each quarter of the map, then the
+/// center
+fn waypoints(map: &Map) -> [Point; 5] {
+    let (x, y) = (
+        map.lower_x() + map.width() / 2,
+        map.lower_y() + map.height() / 2,
+    );
+    let (dx, dy) = (map.width() / 4, map.height() / 4);
+    [
+        Point::new(x - dx, y - dy),
+        Point::new(x + dx, y - dy),
+        Point::new(x + dx, y + dy),
+        Point::new(x - dx, y + dy),
+        Point::new(x, y),
+    ]
+}
+
+/// Pull badly hurt army units back home and have the rest focus the weakest enemy nearby,
+/// depending on the difficulty
+fn micro(brain: &Brain, view: &View, players: &Players, home: Option<Point>, orders: &mut Orders) {
+    let player = brain.player();
+    let difficulty = brain.difficulty();
+    let near_enemy = |point: Point| {
+        view.units.iter().any(|other| {
+            players.hostile(player, other.player) && tiles_apart(other.point, point) <= SIGHT
+        })
+    };
+
+    let mut retreating = Vec::new();
+    let mut fighting = Vec::new();
+    for unit in view.units.iter().filter(|unit| {
+        unit.player == player && !unit.kind.is_worker() && Some(unit.entity) != brain.scout()
+    }) {
+        if !near_enemy(unit.point) {
+            continue;
+        }
+        if difficulty.retreats() && unit.health < RETREAT_HEALTH && home.is_some() {
+            // units already on their way home are left to it
+            if !unit.moving {
+                retreating.push(unit.entity);
+            }
+        } else {
+            fighting.push(unit);
+        }
+    }
+
+    if let Some(home) = home {
+        if !retreating.is_empty() {
+            orders.push(player, Order::Move(retreating, home, Mode::Move));
+        }
+    }
+    if difficulty.focus_fire() {
+        let target = view
+            .units
+            .iter()
+            .filter(|other| {
+                players.hostile(player, other.player)
+                    && fighting
+                        .iter()
+                        .any(|unit| tiles_apart(unit.point, other.point) <= SIGHT)
+            })
+            .min_by_key(|other| other.hp);
+        if let Some(target) = target {
+            // only units switching targets are ordered, so the rest keep their paths
+            let units: Vec<Entity> = fighting
+                .iter()
+                .filter(|unit| unit.focus != Some(target.entity))
+                .map(|unit| unit.entity)
+                .collect();
+            if !units.is_empty() {
+                orders.push(player, Order::Attack(units, target.entity));
+            }
+        }
+    }
+}
+
+/// Send the idle army at any enemy units near home, returning whether home is under attack
+fn defend(brain: &Brain, view: &View, players: &Players, home: Point, orders: &mut Orders) -> bool {
+    let player = brain.player();
+    let threat = view.units.iter().find(|unit| {
+        players.hostile(player, unit.player) && tiles_apart(unit.point, home) <= SIGHT * 2
+    });
+    match threat {
+        Some(threat) => {
+            let army = idle_army(brain, view);
+            if !army.is_empty() {
+                orders.push(player, Order::Move(army, threat.point, Mode::Attack));
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Attack-move the idle army to the enemy base once the wave timer is up and the army is big
+/// enough
+fn attack(brain: &mut Brain, view: &View, orders: &mut Orders) {
+    if !brain.wave_ready() {
+        return;
+    }
+    let target = match brain.enemy_base() {
+        Some(target) => target,
+        None => return,
+    };
+
+    let army = idle_army(brain, view);
+    if army.len() >= brain.wave_size() {
+        orders.push(brain.player(), Order::Move(army, target, Mode::Attack));
+        brain.send_wave();
+    }
+}
+
+/// Return the player's army units that have no orders, leaving out the scout
+fn idle_army(brain: &Brain, view: &View) -> Vec<Entity> {
+    view.units
+        .iter()
+        .filter(|unit| {
+            unit.player == brain.player()
+                && !unit.kind.is_worker()
+                && unit.idle
+                && Some(unit.entity) != brain.scout()
+        })
+        .map(|unit| unit.entity)
+        .collect()
+}
+
+/// Return the number of tiles between two points, counting diagonal steps as one
+fn tiles_apart(a: Point, b: Point) -> i32 {
+    (a.x - b.x).abs().max((a.y - b.y).abs())
+}
@@ -1,3 +1,137 @@
-// pub struct Building;
+use bracket_lib::prelude::Point;
 
-// impl Building {}
+use legion::Entity;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    simulation::save::entity,
+    types::{BuildingKind, Mode, UnitKind},
+};
+
+/// The most units a Building can have queued for production at once
+pub const QUEUE_SIZE: usize = 5;
+
+/// Where newly trained units are sent after leaving a Building
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Rally {
+    /// Move, or attack-move, to a point
+    Point(Point, Mode),
+    /// Follow a friendly unit
+    Unit(#[serde(with = "entity")] Entity),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Building {
+    kind: BuildingKind,
+    hp: (i32, u32),
+    construction: Option<f32>,
+    queue: Vec<UnitKind>,
+    progress: f32,
+    rally: Option<Rally>,
+}
+
+impl Building {
+    pub fn new(kind: BuildingKind) -> Self {
+        Self {
+            kind,
+            hp: (kind.hp(), kind.hp() as u32),
+            construction: None,
+            queue: Vec::new(),
+            progress: 0.0,
+            rally: None,
+        }
+    }
+    /// Create a construction site which starts with a tenth of its HP and gains the rest as it
+    /// is built
+    pub fn site(kind: BuildingKind) -> Self {
+        Self {
+            kind,
+            hp: (kind.hp() / 10, kind.hp() as u32),
+            construction: Some(0.0),
+            queue: Vec::new(),
+            progress: 0.0,
+            rally: None,
+        }
+    }
+
+    /// Advance construction of the Building by the given number of seconds
+    pub fn construct(&mut self, dt: f32) {
+        if let Some(progress) = self.construction {
+            let build_time = self.kind.build_time();
+            let max_hp = self.hp.1 as f32;
+            let built_hp = |progress: f32| (max_hp * 0.9 * progress / build_time) as i32;
+
+            let next = (progress + dt).min(build_time);
+            self.hp.0 += built_hp(next) - built_hp(progress);
+            self.construction = if next >= build_time { None } else { Some(next) };
+        }
+    }
+    pub fn is_constructed(&self) -> bool {
+        self.construction.is_none()
+    }
+
+    /// Add a unit to the end of the production queue, returning false if the queue is full or
+    /// the Building cannot train it
+    pub fn queue_unit(&mut self, kind: UnitKind) -> bool {
+        if self.queue.len() < QUEUE_SIZE && self.kind.trains().contains(&kind) {
+            self.queue.push(kind);
+            true
+        } else {
+            false
+        }
+    }
+    /// Remove the last unit in the production queue so it can be refunded
+    pub fn cancel(&mut self) -> Option<UnitKind> {
+        let kind = self.queue.pop();
+        if self.queue.is_empty() {
+            self.progress = 0.0;
+        }
+        kind
+    }
+    /// Advance production of the unit at the front of the queue by the given number of seconds,
+    /// returning it once it is finished
+    pub fn train(&mut self, dt: f32) -> Option<UnitKind> {
+        let kind = *self.queue.first()?;
+        if !self.is_constructed() {
+            return None;
+        }
+
+        self.progress += dt;
+        if self.progress >= kind.train_time() {
+            self.progress = 0.0;
+            Some(self.queue.remove(0))
+        } else {
+            None
+        }
+    }
+    pub fn set_rally(&mut self, rally: Rally) {
+        self.rally = Some(rally);
+    }
+    pub fn rally(&self) -> Option<Rally> {
+        self.rally
+    }
+
+    pub fn queue(&self) -> &[UnitKind] {
+        &self.queue
+    }
+    /// Return how far through training the unit at the front of the queue is, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        match self.queue.first() {
+            Some(kind) => self.progress / kind.train_time(),
+            None => 0.0,
+        }
+    }
+
+    /// Reduce the Building's HP by the given value
+    pub fn harm(&mut self, x: u32) {
+        self.hp.0 -= x as i32;
+    }
+
+    pub fn kind(&self) -> BuildingKind {
+        self.kind
+    }
+    pub fn hp(&self) -> i32 {
+        self.hp.0
+    }
+}
@@ -1,18 +1,34 @@
-use std::cmp::Ordering;
-
-use rand::Rng;
+use std::{cmp::Ordering, sync::Arc};
 
 use bracket_lib::prelude::*;
 
-use crate::types::Mode;
+use legion::Entity;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    simulation::save::{entity_option, pointf},
+    types::{FlowField, Map, Mode},
+};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GameCell {
+    #[serde(with = "pointf")]
     point: PointF,
+    #[serde(with = "pointf")]
+    previous: PointF,
+    size: (i32, i32),
     symbol: char,
     color: RGB,
     selected: bool,
     destination: Option<Point>,
+    path: Vec<Point>,
+    /// The flow field is shared by every cell in a group order, so a save writes each one once
+    /// and points the cells following it back at it
+    #[serde(skip)]
+    flow: Option<Arc<FlowField>>,
+    #[serde(with = "entity_option")]
+    following: Option<Entity>,
     mode: Mode,
     tic: f32,
     harmed: bool,
@@ -22,19 +38,41 @@ impl GameCell {
     pub fn new(x: i32, y: i32, symbol: char, color: RGB) -> Self {
         Self {
             point: PointF::new(x as f32, y as f32),
+            previous: PointF::new(x as f32, y as f32),
+            size: (1, 1),
             symbol,
             color,
             selected: false,
             destination: None,
+            path: Vec::new(),
+            flow: None,
+            following: None,
             mode: Mode::Select,
             tic: 0.0,
             harmed: false,
         }
     }
 
+    /// Set the footprint of the cell, extending right and down from its point
+    pub fn with_size(mut self, w: i32, h: i32) -> Self {
+        self.size = (w, h);
+        self
+    }
+
     pub fn move_pos(&mut self, point: Point, mode: Mode) {
         self.mode = mode;
         self.destination = Some(point);
+        self.path = Vec::new();
+        self.flow = None;
+        self.following = None;
+    }
+    /// Follow the given shared FlowField until close to the group's targets, then path to the
+    /// cell's own destination
+    pub fn follow_flow(&mut self, flow: Arc<FlowField>) {
+        self.flow = Some(flow);
+    }
+    pub fn flow(&self) -> Option<&Arc<FlowField>> {
+        self.flow.as_ref()
     }
     pub fn move_towards(&mut self, other: Point) {
         if self.destination.is_none() {
@@ -56,25 +94,66 @@ impl GameCell {
             self.move_pos(other, Mode::Attack);
         }
     }
+    /// Keep moving towards the given entity until ordered otherwise
+    pub fn follow(&mut self, target: Entity) {
+        self.following = Some(target);
+    }
+    /// Head towards the current point of the followed entity, waiting once within two tiles or
+    /// within the given range, so units attacking it stand and fire instead of closing in
+    pub fn chase(&mut self, point: Point, range: u32) {
+        if (point.x - self.x()).abs() <= 2 && (point.y - self.y()).abs() <= 2
+            || self.range_rect(range).point_in_rect(point)
+        {
+            if self.destination.is_some() {
+                self.stop_moving();
+            }
+        } else if self.destination.map_or(true, |dest| {
+            (dest.x - point.x).abs() > 1 || (dest.y - point.y).abs() > 1
+        }) {
+            self.mode = Mode::Move;
+            self.destination = Some(point);
+            self.path = Vec::new();
+            self.flow = None;
+        }
+    }
+    pub fn following(&self) -> Option<Entity> {
+        self.following
+    }
+    /// Stop moving and stop following any entity
+    pub fn stop(&mut self) {
+        self.following = None;
+        self.stop_moving();
+    }
     pub fn stop_moving(&mut self) {
         if !self.is_holding() {
             self.mode = Mode::Select
         };
         self.destination = None;
+        self.path = Vec::new();
+        self.flow = None;
     }
     pub fn is_moving(&self) -> bool {
         self.mode == Mode::Move
     }
+    /// Return whether the cell is still on its way to a destination
+    pub fn has_destination(&self) -> bool {
+        self.destination.is_some()
+    }
 
     pub fn hold(&mut self) {
+        self.following = None;
         self.mode = Mode::Hold;
         self.destination = None;
+        self.path = Vec::new();
+        self.flow = None;
     }
     pub fn is_holding(&self) -> bool {
         self.mode == Mode::Hold
     }
 
-    pub fn update(&mut self, dt: f32, speed: f32) {
+    pub fn update(&mut self, dt: f32, speed: f32, map: &Map) {
+        self.previous = self.point;
+
         if self.tic > 0.6 {
             self.harmed = false;
         }
@@ -87,42 +166,65 @@ impl GameCell {
         }
 
         if let Some(dest) = self.destination {
-            let distx = dest.x as f32 - self.point.x;
-            let disty = dest.y as f32 - self.point.y;
-            let dist = (distx * distx + disty * disty).sqrt();
+            let near_targets = self
+                .flow
+                .as_ref()
+                .map_or(false, |flow| flow.cost(map, self.point()) <= 2.0);
+            if near_targets {
+                self.flow = None;
+                self.path = Vec::new();
+            }
 
-            if dist != 0.0 {
-                self.point.x += distx / dist * speed * dt;
-                self.point.y += disty / dist * speed * dt;
+            if self.path_blocked(map) {
+                self.path = match &self.flow {
+                    Some(flow) => flow.next_step(map, self.point()).into_iter().collect(),
+                    None => map.path(self.point(), dest),
+                };
             }
 
-            if Rect::with_exact(dest.x - 1, dest.y - 1, dest.x + 1, dest.y + 1)
-                .point_in_rect(self.point())
-            {
+            if let Some(next) = self.path.last() {
+                let distx = next.x as f32 - self.point.x;
+                let disty = next.y as f32 - self.point.y;
+                let dist = (distx * distx + disty * disty).sqrt();
+
+                if dist <= speed * dt {
+                    self.point = PointF::new(next.x as f32, next.y as f32);
+                    self.path.pop();
+                } else {
+                    self.point.x += distx / dist * speed * dt;
+                    self.point.y += disty / dist * speed * dt;
+                }
+            } else {
+                // either the destination has been reached or there is no route to it
                 self.stop_moving();
             }
-        } else {
-            self.point.x = self.point.x.round();
-            self.point.y = self.point.y.round();
         }
     }
 
-    /// Randomly move the cell in one of 8 directions
-    pub fn bump(&mut self) {
-        if self.tic >= 0.1 {
-            let (a, b) = match rand::thread_rng().gen_range(0, 7) {
-                0 => (0.0, -1.0),
-                1 => (1.0, -1.0),
-                2 => (1.0, 0.0),
-                3 => (1.0, 1.0),
-                4 => (0.0, 1.0),
-                5 => (-1.0, 1.0),
-                6 => (-1.0, 0.0),
-                _ => (-1.0, -1.0),
-            };
-            self.point.x += a;
-            self.point.y += b;
-            self.tic = 0.0;
+    /// Return whether the current path needs to be recomputed, either because there is no path,
+    /// the next waypoint has become impassable, or the cell has been pushed away from it
+    fn path_blocked(&self, map: &Map) -> bool {
+        match self.path.last() {
+            Some(next) => {
+                let distx = next.x as f32 - self.point.x;
+                let disty = next.y as f32 - self.point.y;
+                !map.is_walkable(*next) || distx * distx + disty * disty > 4.0
+            }
+            None => true,
+        }
+    }
+
+    /// Push the cell by the given offset, sliding along any impassable tiles in the way
+    pub fn nudge(&mut self, x: f32, y: f32, map: &Map) {
+        let pushed = |px: f32, py: f32| Point::new(px.round() as i32, py.round() as i32);
+
+        if map.is_walkable(pushed(self.point.x + x, self.point.y + y)) {
+            self.point.x += x;
+            self.point.y += y;
+        } else if map.is_walkable(pushed(self.point.x + x, self.point.y)) {
+            self.point.x += x;
+        } else if map.is_walkable(pushed(self.point.x, self.point.y + y)) {
+            self.point.y += y;
         }
     }
 
@@ -137,6 +239,22 @@ impl GameCell {
         )
     }
 
+    /// Return the Rect covered by the cell's footprint
+    pub fn footprint(&self) -> Rect {
+        Rect::with_size(self.x(), self.y(), self.size.0, self.size.1)
+    }
+    /// Return whether the given point is within the cell's footprint
+    pub fn contains(&self, point: Point) -> bool {
+        self.footprint().point_in_rect(point)
+    }
+    /// Return the point within the cell's footprint closest to the given point
+    pub fn nearest_point(&self, other: Point) -> Point {
+        Point::new(
+            other.x.max(self.x()).min(self.x() + self.size.0 - 1),
+            other.y.max(self.y()).min(self.y() + self.size.1 - 1),
+        )
+    }
+
     /// Select the cell
     pub fn select(&mut self) {
         self.selected = true;
@@ -152,7 +270,18 @@ impl GameCell {
     }
 
     pub fn point(&self) -> Point {
-        self.point.into()
+        Point::new(self.x(), self.y())
+    }
+    pub fn position(&self) -> PointF {
+        self.point
+    }
+    /// Return the point the cell is drawn at, the given fraction of the way from where it was
+    /// before the last tick to where it is now
+    pub fn render_point(&self, alpha: f32) -> Point {
+        Point::new(
+            (self.previous.x + (self.point.x - self.previous.x) * alpha).round() as i32,
+            (self.previous.y + (self.point.y - self.previous.y) * alpha).round() as i32,
+        )
     }
     pub fn x(&self) -> i32 {
         self.point.x.round() as i32
@@ -186,4 +315,7 @@ impl GameCell {
     pub fn selected(&self) -> bool {
         self.selected
     }
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
 }
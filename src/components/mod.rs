@@ -1,7 +1,13 @@
 mod building;
 mod gamecell;
+mod player;
+mod resource;
 mod unit;
+mod worker;
 
-// pub use building::Building;
+pub use building::{Building, Rally, QUEUE_SIZE};
 pub use gamecell::GameCell;
-pub use unit::Unit;
+pub use player::Player;
+pub use resource::ResourceNode;
+pub use unit::{Unit, MAX_ATTACK_RATE};
+pub use worker::{Task, Worker};
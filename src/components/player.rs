@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// A component marking the player that owns an entity
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Player {
+    id: usize,
+}
+
+impl Player {
+    pub fn new(id: usize) -> Self {
+        Self { id }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
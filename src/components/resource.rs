@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::ResourceKind;
+
+/// A component for a mineral field or gas vent that workers gather from
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ResourceNode {
+    kind: ResourceKind,
+    amount: u32,
+}
+
+impl ResourceNode {
+    pub fn new(kind: ResourceKind) -> Self {
+        Self {
+            kind,
+            amount: kind.amount(),
+        }
+    }
+
+    /// Remove up to the given amount from the node and return how much was taken
+    pub fn take(&mut self, x: u32) -> u32 {
+        let taken = x.min(self.amount);
+        self.amount -= taken;
+        taken
+    }
+    pub fn is_depleted(&self) -> bool {
+        self.amount == 0
+    }
+
+    pub fn kind(&self) -> ResourceKind {
+        self.kind
+    }
+    pub fn amount(&self) -> u32 {
+        self.amount
+    }
+}
@@ -1,6 +1,16 @@
-use crate::types::{Race, UnitKind};
+use legion::Entity;
 
-#[derive(Clone, Debug)]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    simulation::save::entity_option,
+    types::{Race, UnitKind},
+};
+
+/// The most seconds a unit can take between attacks, after which its attack timer starts over
+pub const MAX_ATTACK_RATE: f32 = 5.0;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 
 pub struct Unit {
     race: Race,
@@ -11,6 +21,9 @@ pub struct Unit {
     attack_rate: f32,
     range: u32,
     follow_dist: u32,
+    sight: u32,
+    #[serde(with = "entity_option")]
+    focus: Option<Entity>,
     tic: f32,
 }
 
@@ -25,9 +38,21 @@ impl Unit {
             attack_rate: 1.0,
             range: 0,
             follow_dist: 5,
+            sight: 7,
+            focus: None,
             tic: 0.0,
         }
     }
+    /// Create a Unit with the stats of the given UnitKind
+    pub fn from_kind(kind: UnitKind) -> Self {
+        let def = kind.def();
+        Unit::new(def.race, kind, def.hp)
+            .with_damage(def.damage)
+            .with_attack_rate(def.attack_rate)
+            .with_range(def.range, def.follow_dist)
+            .with_speed(def.speed)
+            .with_sight(def.sight)
+    }
     pub fn with_speed(mut self, x: f32) -> Self {
         self.speed = x;
         self
@@ -36,6 +61,14 @@ impl Unit {
         self.damage = x;
         self
     }
+    pub fn with_sight(mut self, x: u32) -> Self {
+        self.sight = x;
+        self
+    }
+    pub fn with_attack_rate(mut self, x: f32) -> Self {
+        self.attack_rate = x;
+        self
+    }
     pub fn with_range(mut self, r: u32, f: u32) -> Self {
         self.range = r;
         self.follow_dist = f;
@@ -47,8 +80,13 @@ impl Unit {
         self.hp.0 -= x as i32;
     }
 
+    /// Set the entity the Unit attacks ahead of anything else in range
+    pub fn set_focus(&mut self, target: Option<Entity>) {
+        self.focus = target;
+    }
+
     pub fn tic(&mut self, dt: f32) {
-        if self.tic > 5.0 {
+        if self.tic > MAX_ATTACK_RATE {
             self.tic = 0.0;
         } else {
             self.tic += dt;
@@ -67,9 +105,15 @@ impl Unit {
     pub fn hp(&self) -> i32 {
         self.hp.0
     }
+    pub fn max_hp(&self) -> u32 {
+        self.hp.1
+    }
     pub fn speed(&self) -> f32 {
         self.speed
     }
+    pub fn damage(&self) -> u32 {
+        self.damage
+    }
     pub fn attack(&self) -> Option<u32> {
         if self.tic >= self.attack_rate {
             Some(self.damage)
@@ -83,4 +127,11 @@ impl Unit {
     pub fn follow_dist(&self) -> u32 {
         self.follow_dist
     }
+    /// Return how many tiles away the Unit can see
+    pub fn sight(&self) -> u32 {
+        self.sight
+    }
+    pub fn focus(&self) -> Option<Entity> {
+        self.focus
+    }
 }
@@ -0,0 +1,108 @@
+use bracket_lib::prelude::Point;
+
+use legion::Entity;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{simulation::save::EntityRef, types::ResourceKind};
+
+/// The number of seconds a worker spends mining before it has a full load
+const GATHER_TIME: f32 = 1.5;
+
+/// What a worker is currently doing in its gather/return cycle
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Task {
+    Idle,
+    Gather,
+    Return,
+}
+
+/// A component for units that gather resources and carry them back to a depot
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Worker {
+    task: Task,
+    #[serde(with = "node")]
+    node: Option<(Entity, Point, ResourceKind)>,
+    carrying: Option<(ResourceKind, u32)>,
+    progress: f32,
+}
+
+impl Worker {
+    pub fn new() -> Self {
+        Self {
+            task: Task::Idle,
+            node: None,
+            carrying: None,
+            progress: 0.0,
+        }
+    }
+
+    /// Start gathering from the given node, first returning any load already carried
+    pub fn gather(&mut self, node: Entity, point: Point, kind: ResourceKind) {
+        self.node = Some((node, point, kind));
+        self.progress = 0.0;
+        self.task = if self.carrying.is_some() {
+            Task::Return
+        } else {
+            Task::Gather
+        };
+    }
+    pub fn idle(&mut self) {
+        self.task = Task::Idle;
+        self.progress = 0.0;
+    }
+    pub fn set_task(&mut self, task: Task) {
+        self.task = task;
+    }
+
+    /// Mine for the given number of seconds and return whether a full load is ready
+    pub fn mine(&mut self, dt: f32) -> bool {
+        self.progress += dt;
+        if self.progress >= GATHER_TIME {
+            self.progress = 0.0;
+            true
+        } else {
+            false
+        }
+    }
+    pub fn load(&mut self, kind: ResourceKind, x: u32) {
+        self.carrying = Some((kind, x));
+    }
+    pub fn unload(&mut self) -> Option<(ResourceKind, u32)> {
+        self.carrying.take()
+    }
+
+    pub fn task(&self) -> Task {
+        self.task
+    }
+    pub fn node(&self) -> Option<(Entity, Point, ResourceKind)> {
+        self.node
+    }
+    pub fn carrying(&self) -> Option<(ResourceKind, u32)> {
+        self.carrying
+    }
+}
+
+/// Saves the node a worker gathers from with its entity as an EntityRef
+mod node {
+    use super::*;
+
+    type Node = Option<(Entity, Point, ResourceKind)>;
+
+    pub fn serialize<S: Serializer>(node: &Node, serializer: S) -> Result<S::Ok, S::Error> {
+        node.map(|(e, point, kind)| (EntityRef(e), point, kind))
+            .serialize(serializer)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Node, D::Error> {
+        Ok(
+            Option::<(EntityRef, Point, ResourceKind)>::deserialize(deserializer)?
+                .map(|(e, point, kind)| (e.0, point, kind)),
+        )
+    }
+}
+
+impl Default for Worker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
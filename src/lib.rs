@@ -1,5 +1,16 @@
+mod ai;
 mod components;
+mod simulation;
 mod state;
 mod types;
 
+pub use components::{Building, GameCell, Player, Rally, ResourceNode, Unit, Worker};
+pub use simulation::{
+    BuildingPlacement, MapDef, NodePlacement, Playback, PlayerDef, Replay, Scenario, Simulation,
+    TileFill, UnitPlacement, TICK_SECONDS,
+};
 pub use state::State;
+pub use types::{
+    load_unit_defs, BuildingKind, Command, Condition, Difficulty, Map, Mode, Outcome, PlayerInfo,
+    PlayerStats, Players, Race, ResourceKind, Tile, UnitDef, UnitDefs, UnitDefsError, UnitKind,
+};
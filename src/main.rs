@@ -1,10 +1,22 @@
+use std::{io::ErrorKind, process};
+
 use winit::event_loop::EventLoop;
 
 use bracket_lib::prelude::*;
 
-use text_rts::State;
+use text_rts::{load_unit_defs, State, UnitDefsError};
+
+/// Where the unit definitions are read from, falling back on the built-in ones if it is missing
+const UNITS_PATH: &str = "data/units.ron";
 
 fn main() -> BError {
+    if let Err(e) = load_unit_defs(UNITS_PATH) {
+        if !matches!(&e, UnitDefsError::Io(e) if e.kind() == ErrorKind::NotFound) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+
     let tw = 24;
     let th = 24;
 
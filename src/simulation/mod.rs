@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+
+use bracket_lib::prelude::*;
+
+use legion::*;
+
+mod replay;
+pub(crate) mod save;
+mod scenario;
+mod systems;
+#[cfg(test)]
+mod tests;
+
+pub use replay::{Playback, Replay};
+pub use scenario::{
+    BuildingPlacement, MapDef, NodePlacement, PlayerDef, Scenario, TileFill, UnitPlacement,
+};
+
+use crate::{
+    ai::{self, Brain, Brains},
+    components::{Building, GameCell, Player, ResourceNode, Unit, Worker},
+    types::{
+        BuildingKind, Command, Difficulty, Effect, Effects, Map, Order, Orders, Outcome,
+        PlayerInfo, PlayerStats, Players, ResourceKind, Rng, Stats, Time, UnitKind, Vision,
+    },
+};
+
+/// The number of seconds of game time simulated by each tick of the schedule
+pub const TICK_SECONDS: f32 = 1.0 / 30.0;
+
+/// Create the components for a unit of the given kind at the given point
+fn unit(kind: UnitKind, x: i32, y: i32, player: Player) -> (GameCell, Unit, Player) {
+    (
+        GameCell::new(x, y, kind.symbol(), kind.color()),
+        Unit::from_kind(kind),
+        player,
+    )
+}
+
+/// Create the components for a worker of the given kind at the given point
+fn worker(kind: UnitKind, x: i32, y: i32, player: Player) -> (GameCell, Unit, Player, Worker) {
+    let (cell, unit, player) = unit(kind, x, y, player);
+    (cell, unit, player, Worker::new())
+}
+
+/// Create the components for a resource node at the given point
+fn resource_node(kind: ResourceKind, x: i32, y: i32) -> (GameCell, ResourceNode) {
+    (
+        GameCell::new(x, y, kind.symbol(), kind.color()),
+        ResourceNode::new(kind),
+    )
+}
+
+/// Create the components for a building with its top left corner at the given point
+fn building(
+    building: Building,
+    x: i32,
+    y: i32,
+    player: Player,
+    color: RGB,
+) -> (GameCell, Building, Player) {
+    let (w, h) = building.kind().size();
+    let symbol = building.kind().art()[0].chars().next().unwrap_or(' ');
+    (
+        GameCell::new(x, y, symbol, color).with_size(w, h),
+        building,
+        player,
+    )
+}
+
+/// Return every entity in the world in the order they are stored, which is the same from one run of
+/// a match to the next given the same seed and commands
+fn entities(world: &World) -> Vec<Entity> {
+    let mut entities = Vec::new();
+    let mut query = <(Read<GameCell>,)>::query();
+    for chunk in query.iter_chunks(world) {
+        for (e, _) in chunk.into_iter_entities() {
+            entities.push(e);
+        }
+    }
+    entities
+}
+
+/// Create a world with a place for every set of components an entity in a match has, made in the
+/// same order every time so entities are stored in the same order whether the match was played from
+/// the start or loaded from a save
+fn new_world() -> World {
+    let mut world = World::default();
+    world.extend(Vec::<(GameCell, Unit, Player, Worker)>::new());
+    world.extend(Vec::<(GameCell, Unit, Player)>::new());
+    world.extend(Vec::<(GameCell, Building, Player)>::new());
+    world.extend(Vec::<(GameCell, ResourceNode)>::new());
+    world
+}
+
+/// A match that runs without any rendering or input, advanced one fixed tick at a time and driven
+/// by the Commands of its players
+pub struct Simulation {
+    world: World,
+    resources: Resources,
+    schedule: Schedule,
+    selections: HashMap<usize, Vec<Entity>>,
+    replay: Replay,
+}
+
+impl Simulation {
+    /// Create an empty match on the map between the players, with every random choice drawn from
+    /// the seed
+    pub fn new(map: Map, players: Players, seed: u64) -> Self {
+        let mut resources = Resources::default();
+        resources.insert(Vision::new(&map));
+        resources.insert(map);
+        resources.insert(players);
+        resources.insert(Time::new(TICK_SECONDS));
+        resources.insert(Rng::new(seed));
+        resources.insert(Orders::new());
+        resources.insert(Brains::new());
+        resources.insert(Stats::new());
+        resources.insert(Effects::new());
+        resources.insert(Outcome::new(Vec::new()));
+
+        let schedule = Schedule::builder()
+            .add_system(ai::think())
+            .add_system(systems::apply_orders())
+            .add_system(systems::update_units())
+            .add_system(systems::produce_units())
+            .add_system(systems::separate_units())
+            .add_system(systems::follow_units())
+            .add_system(systems::attack_units())
+            .add_system(systems::gather_resources())
+            .add_system(systems::clear_units())
+            .add_system(systems::count_supply())
+            .flush()
+            .add_system(systems::check_victory())
+            .add_system(systems::update_vision())
+            .build();
+
+        Self {
+            world: new_world(),
+            resources,
+            schedule,
+            selections: HashMap::new(),
+            replay: Replay::new(seed),
+        }
+    }
+
+    /// Create a match set up as the scenario describes
+    pub fn from_scenario(scenario: &Scenario, seed: u64) -> Self {
+        let mut sim = Simulation::new(scenario.map(), scenario.players(), seed);
+        sim.replay.set_scenario(scenario.clone());
+        sim.resources.insert(Outcome::new(scenario.victory.clone()));
+
+        for placement in scenario.units.iter() {
+            for _ in 0..placement.count {
+                sim.spawn_unit(
+                    placement.kind,
+                    Point::new(placement.x, placement.y),
+                    Player::new(placement.player),
+                );
+            }
+        }
+        for placement in scenario.buildings.iter() {
+            sim.spawn_building(
+                placement.kind,
+                Point::new(placement.x, placement.y),
+                Player::new(placement.player),
+            );
+        }
+        for placement in scenario.nodes.iter() {
+            sim.spawn_node(placement.kind, Point::new(placement.x, placement.y));
+        }
+
+        sim
+    }
+
+    /// Create the standard three player skirmish of Bionic against Bug and Alien
+    pub fn skirmish(seed: u64) -> Self {
+        Simulation::from_scenario(&Scenario::skirmish(), seed)
+    }
+
+    /// Add a unit of the given kind, returning its entity
+    pub fn spawn_unit(&mut self, kind: UnitKind, point: Point, player: Player) -> Entity {
+        if kind.is_worker() {
+            self.world.push(worker(kind, point.x, point.y, player))
+        } else {
+            self.world.push(unit(kind, point.x, point.y, player))
+        }
+    }
+
+    /// Add a finished building with its top left corner at the point, returning its entity
+    pub fn spawn_building(&mut self, kind: BuildingKind, point: Point, player: Player) -> Entity {
+        let color = self
+            .resources
+            .get::<Players>()
+            .unwrap()
+            .get(player)
+            .map_or(RGB::named(WHITE), |info| info.color());
+        let components = building(Building::new(kind), point.x, point.y, player, color);
+        self.resources
+            .get_mut::<Map>()
+            .unwrap()
+            .block(components.0.footprint());
+        self.world.push(components)
+    }
+
+    /// Add a resource node at the point, returning its entity
+    pub fn spawn_node(&mut self, kind: ResourceKind, point: Point) -> Entity {
+        let components = resource_node(kind, point.x, point.y);
+        self.resources
+            .get_mut::<Map>()
+            .unwrap()
+            .block(components.0.footprint());
+        self.world.push(components)
+    }
+
+    /// Hand every player but the local one to a computer opponent of the given difficulty
+    pub fn add_computers(&mut self, difficulty: Difficulty) {
+        let mut players = self.resources.get_mut::<Players>().unwrap();
+        let mut brains = Brains::new();
+        for player in players.computers() {
+            brains = brains.with_brain(Brain::new(player, difficulty));
+            if let Some(info) = players.get_mut(player) {
+                info.set_income(difficulty.income());
+            }
+        }
+        drop(players);
+
+        self.resources.insert(brains);
+        self.replay.set_difficulty(difficulty);
+    }
+
+    /// Carry out a command on behalf of the player, turning it into an order for the next tick
+    pub fn command(&mut self, player: Player, command: Command) {
+        let tick = self.tick();
+        self.replay
+            .record(tick, player, &command, &entities(&self.world));
+
+        let selected = self
+            .selections
+            .get(&player.id())
+            .cloned()
+            .unwrap_or_default();
+
+        let order = match command {
+            Command::Select(entities) => {
+                self.selections.insert(player.id(), entities);
+                None
+            }
+            Command::Move(point, mode) => Some(Order::Move(selected, point, mode)),
+            Command::Attack(target) => Some(Order::Attack(selected, target)),
+            Command::Hold => Some(Order::Hold(selected)),
+            Command::Stop => Some(Order::Stop(selected)),
+            Command::Gather(node) => Some(Order::Gather(selected, node)),
+            Command::Build(kind, origin) => Some(Order::Build(kind, origin)),
+            Command::Train(kind) => self
+                .selected_buildings(player, &selected)
+                .into_iter()
+                .filter(|(_, building)| building.kind().trains().contains(&kind))
+                .min_by_key(|(_, building)| building.queue().len())
+                .map(|(e, _)| Order::Train(e, kind)),
+            Command::Cancel => self
+                .selected_buildings(player, &selected)
+                .into_iter()
+                .find(|(_, building)| !building.queue().is_empty())
+                .map(|(e, _)| Order::Cancel(e)),
+            Command::Rally(rally) => Some(Order::Rally(selected, rally)),
+        };
+
+        if let Some(order) = order {
+            self.resources
+                .get_mut::<Orders>()
+                .unwrap()
+                .push(player, order);
+        }
+    }
+
+    /// Return the constructed production buildings among the entities that the player owns
+    fn selected_buildings(&self, player: Player, selected: &[Entity]) -> Vec<(Entity, Building)> {
+        selected
+            .iter()
+            .filter_map(|e| {
+                let entry = self.world.entry_ref(*e).ok()?;
+                let owner = entry.get_component::<Player>().ok()?;
+                let building = entry.get_component::<Building>().ok()?;
+                if *owner == player
+                    && building.is_constructed()
+                    && !building.kind().trains().is_empty()
+                {
+                    Some((*e, building.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Advance the match by one fixed tick
+    pub fn step(&mut self) {
+        self.resources.get_mut::<Effects>().unwrap().clear();
+        self.schedule.execute(&mut self.world, &mut self.resources);
+        self.resources.get_mut::<Time>().unwrap().advance();
+    }
+
+    /// Advance the match by the given number of ticks
+    pub fn run(&mut self, ticks: u64) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    /// Return the number of ticks simulated so far
+    pub fn tick(&self) -> u64 {
+        self.resources.get::<Time>().unwrap().tick()
+    }
+    /// Return the seed every random choice in the match is drawn from
+    pub fn seed(&self) -> u64 {
+        self.resources.get::<Rng>().unwrap().seed()
+    }
+
+    /// Return the Replay of the match so far
+    pub fn replay(&self) -> Replay {
+        let mut replay = self.replay.clone();
+        replay.set_length(self.tick());
+        replay
+    }
+
+    /// Return the units the player owns that are still alive
+    pub fn units(&self, player: Player) -> Vec<Entity> {
+        let mut units = Vec::new();
+        let mut query = <(Read<Player>,)>::query().filter(component::<Unit>());
+        for chunk in query.iter_chunks(&self.world) {
+            for (e, (owner,)) in chunk.into_iter_entities() {
+                if *owner == player {
+                    units.push(e);
+                }
+            }
+        }
+        units
+    }
+
+    /// Return whether the player has no units or buildings left
+    pub fn is_eliminated(&self, player: Player) -> bool {
+        let mut query =
+            <(Read<Player>,)>::query().filter(component::<Unit>() | component::<Building>());
+        query.iter(&self.world).all(|(owner,)| *owner != player)
+    }
+
+    /// Return the point an entity is at, if it still exists
+    pub fn position(&self, e: Entity) -> Option<Point> {
+        self.world.entry_ref(e).ok().and_then(|entry| {
+            entry
+                .get_component::<GameCell>()
+                .ok()
+                .map(|cell| cell.point())
+        })
+    }
+
+    /// Return the match's victory conditions and whether one has been met
+    pub fn outcome(&self) -> Outcome {
+        self.resources.get::<Outcome>().unwrap().clone()
+    }
+    /// Return the units the player has produced and lost and the damage they have dealt
+    pub fn stats(&self, player: Player) -> PlayerStats {
+        self.resources.get::<Stats>().unwrap().get(player)
+    }
+    /// Return the damage and deaths of the last tick, to be shown over the map
+    pub fn take_effects(&mut self) -> Vec<Effect> {
+        self.resources.get_mut::<Effects>().unwrap().take()
+    }
+    /// Return the current state of a player
+    pub fn player(&self, player: Player) -> Option<PlayerInfo> {
+        self.resources
+            .get::<Players>()
+            .unwrap()
+            .get(player)
+            .cloned()
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+    pub fn resources(&self) -> &Resources {
+        &self.resources
+    }
+    pub fn resources_mut(&mut self) -> &mut Resources {
+        &mut self.resources
+    }
+}
@@ -0,0 +1,218 @@
+use std::{fs, io, path::Path};
+
+use bracket_lib::prelude::Point;
+
+use legion::Entity;
+
+use serde::{Deserialize, Serialize};
+
+use super::{entities, Scenario, Simulation};
+use crate::{
+    components::{Player, Rally},
+    types::{BuildingKind, Command, Difficulty, Mode, UnitKind},
+};
+
+/// The version of the replay format written by this build, bumped whenever old replays would no
+/// longer play back the same
+const REPLAY_VERSION: u32 = 3;
+
+/// A Command as written to a replay, with every entity replaced by its place in the world
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum Recorded {
+    Select(Vec<usize>),
+    Move(i32, i32, Mode),
+    Attack(usize),
+    Hold,
+    Stop,
+    Gather(usize),
+    Build(BuildingKind, i32, i32),
+    Train(UnitKind),
+    Cancel,
+    RallyPoint(i32, i32, Mode),
+    RallyUnit(usize),
+}
+
+impl Recorded {
+    /// Record the command given the entities in the world, returning None if it refers to an
+    /// entity that is gone
+    fn new(command: &Command, entities: &[Entity]) -> Option<Self> {
+        let index = |e: &Entity| entities.iter().position(|other| other == e);
+        Some(match command {
+            Command::Select(selected) => {
+                Recorded::Select(selected.iter().filter_map(index).collect())
+            }
+            Command::Move(point, mode) => Recorded::Move(point.x, point.y, *mode),
+            Command::Attack(target) => Recorded::Attack(index(target)?),
+            Command::Hold => Recorded::Hold,
+            Command::Stop => Recorded::Stop,
+            Command::Gather(node) => Recorded::Gather(index(node)?),
+            Command::Build(kind, origin) => Recorded::Build(*kind, origin.x, origin.y),
+            Command::Train(kind) => Recorded::Train(*kind),
+            Command::Cancel => Recorded::Cancel,
+            Command::Rally(Rally::Point(point, mode)) => {
+                Recorded::RallyPoint(point.x, point.y, *mode)
+            }
+            Command::Rally(Rally::Unit(target)) => Recorded::RallyUnit(index(target)?),
+        })
+    }
+
+    /// Return the Command again given the entities in the world
+    fn command(&self, entities: &[Entity]) -> Option<Command> {
+        let entity = |i: &usize| entities.get(*i).copied();
+        Some(match self {
+            Recorded::Select(selected) => {
+                Command::Select(selected.iter().filter_map(entity).collect())
+            }
+            Recorded::Move(x, y, mode) => Command::Move(Point::new(*x, *y), *mode),
+            Recorded::Attack(target) => Command::Attack(entity(target)?),
+            Recorded::Hold => Command::Hold,
+            Recorded::Stop => Command::Stop,
+            Recorded::Gather(node) => Command::Gather(entity(node)?),
+            Recorded::Build(kind, x, y) => Command::Build(*kind, Point::new(*x, *y)),
+            Recorded::Train(kind) => Command::Train(*kind),
+            Recorded::Cancel => Command::Cancel,
+            Recorded::RallyPoint(x, y, mode) => {
+                Command::Rally(Rally::Point(Point::new(*x, *y), *mode))
+            }
+            Recorded::RallyUnit(target) => Command::Rally(Rally::Unit(entity(target)?)),
+        })
+    }
+}
+
+/// Everything needed to play a match again: its seed, the scenario it was set up from, the
+/// difficulty of the computer players and every command given along with the tick it was given on
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    version: u32,
+    seed: u64,
+    scenario: Option<Scenario>,
+    difficulty: Option<Difficulty>,
+    length: u64,
+    commands: Vec<(u64, usize, Recorded)>,
+}
+
+impl Replay {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            seed,
+            scenario: None,
+            difficulty: None,
+            length: 0,
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn set_scenario(&mut self, scenario: Scenario) {
+        self.scenario = Some(scenario);
+    }
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = Some(difficulty);
+    }
+    /// Add a command given by the player on the tick, given the entities in the world
+    pub fn record(&mut self, tick: u64, player: Player, command: &Command, entities: &[Entity]) {
+        if let Some(recorded) = Recorded::new(command, entities) {
+            self.commands.push((tick, player.id(), recorded));
+        }
+        self.length = self.length.max(tick);
+    }
+    /// Mark the match as having run until the tick
+    pub fn set_length(&mut self, tick: u64) {
+        self.length = self.length.max(tick);
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let s = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        fs::write(path, s)
+    }
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let replay: Replay = ron::de::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        if replay.version != REPLAY_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "replay version {} can't be played by this version of the game, which plays \
+                     version {}",
+                    replay.version, REPLAY_VERSION
+                ),
+            ));
+        }
+        Ok(replay)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn scenario(&self) -> Option<&Scenario> {
+        self.scenario.as_ref()
+    }
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        self.difficulty
+    }
+    /// Return the number of ticks the match ran for
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+}
+
+/// Plays a Replay back by giving a Simulation each recorded command on the tick it was given
+#[derive(Clone, Debug)]
+pub struct Playback {
+    replay: Replay,
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, next: 0 }
+    }
+
+    /// Return a Simulation at the start of the replayed match
+    pub fn restart(&mut self) -> Simulation {
+        self.next = 0;
+        let mut sim = match &self.replay.scenario {
+            Some(scenario) => Simulation::from_scenario(scenario, self.replay.seed),
+            None => Simulation::skirmish(self.replay.seed),
+        };
+        if let Some(difficulty) = self.replay.difficulty {
+            sim.add_computers(difficulty);
+        }
+        sim
+    }
+
+    /// Give the Simulation the commands due on its current tick and advance it by one tick
+    pub fn step(&mut self, sim: &mut Simulation) {
+        let mut world_entities = None;
+        while let Some((tick, player, recorded)) = self.replay.commands.get(self.next) {
+            if *tick > sim.tick() {
+                break;
+            }
+            let world_entities = world_entities.get_or_insert_with(|| entities(sim.world()));
+            if let Some(command) = recorded.command(world_entities) {
+                sim.command(Player::new(*player), command);
+            }
+            self.next += 1;
+        }
+        sim.step();
+    }
+
+    /// Move the Simulation to the tick, playing the match again from the start to go backwards
+    pub fn seek(&mut self, sim: &mut Simulation, tick: u64) {
+        if tick < sim.tick() {
+            *sim = self.restart();
+        }
+        while sim.tick() < tick.min(self.replay.length) {
+            self.step(sim);
+        }
+    }
+
+    /// Return whether the Simulation has reached the end of the replayed match
+    pub fn is_finished(&self, sim: &Simulation) -> bool {
+        sim.tick() >= self.replay.length
+    }
+    pub fn length(&self) -> u64 {
+        self.replay.length
+    }
+}
@@ -0,0 +1,374 @@
+use std::{cell::RefCell, collections::HashMap, fs, io, path::Path, sync::Arc};
+
+use bracket_lib::prelude::PointF;
+
+use legion::*;
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{entities, new_world, Replay, Simulation};
+use crate::{
+    ai::Brains,
+    components::{Building, GameCell, Player, ResourceNode, Unit, Worker},
+    types::{FlowField, Map, Orders, Outcome, Players, Rng, Stats, Time, Vision},
+};
+
+/// The version of the save format written by this build, bumped whenever old saves could no
+/// longer be read
+const SAVE_VERSION: u32 = 8;
+
+thread_local! {
+    /// The entities written to or read from the save in progress, indexed the way they are
+    /// referred to in the file
+    static REFERENCES: RefCell<Vec<Entity>> = RefCell::new(Vec::new());
+    /// A world whose entities stand in for those referred to in a save that no longer exist
+    static PLACEHOLDERS: RefCell<World> = RefCell::new(World::default());
+}
+
+/// An entity as written to a save: its index among the entities in the file, or past the end of
+/// them for an entity that was already gone
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EntityRef(pub Entity);
+
+impl Serialize for EntityRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let index = REFERENCES.with(|references| {
+            let mut references = references.borrow_mut();
+            match references.iter().position(|e| *e == self.0) {
+                Some(index) => index,
+                None => {
+                    references.push(self.0);
+                    references.len() - 1
+                }
+            }
+        });
+        index.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EntityRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let index = usize::deserialize(deserializer)?;
+        let e = REFERENCES.with(|references| {
+            let mut references = references.borrow_mut();
+            while references.len() <= index {
+                references.push(PLACEHOLDERS.with(|world| world.borrow_mut().push((index,))));
+            }
+            references[index]
+        });
+        Ok(EntityRef(e))
+    }
+}
+
+/// Saves an Entity field as an EntityRef
+pub mod entity {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(e: &Entity, serializer: S) -> Result<S::Ok, S::Error> {
+        EntityRef(*e).serialize(serializer)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Entity, D::Error> {
+        Ok(EntityRef::deserialize(deserializer)?.0)
+    }
+}
+
+/// Saves an Option<Entity> field as an Option<EntityRef>
+pub mod entity_option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(e: &Option<Entity>, serializer: S) -> Result<S::Ok, S::Error> {
+        e.map(EntityRef).serialize(serializer)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Entity>, D::Error> {
+        Ok(Option::<EntityRef>::deserialize(deserializer)?.map(|e| e.0))
+    }
+}
+
+/// Saves a Vec<Entity> field as a Vec<EntityRef>
+pub mod entity_list {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(list: &[Entity], serializer: S) -> Result<S::Ok, S::Error> {
+        list.iter()
+            .copied()
+            .map(EntityRef)
+            .collect::<Vec<EntityRef>>()
+            .serialize(serializer)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Entity>, D::Error> {
+        Ok(Vec::<EntityRef>::deserialize(deserializer)?
+            .into_iter()
+            .map(|e| e.0)
+            .collect())
+    }
+}
+
+/// Saves a Vec<Vec<Entity>> field as lists of EntityRefs
+pub mod entity_lists {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        lists: &[Vec<Entity>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        lists
+            .iter()
+            .map(|list| list.iter().copied().map(EntityRef).collect())
+            .collect::<Vec<Vec<EntityRef>>>()
+            .serialize(serializer)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Vec<Entity>>, D::Error> {
+        Ok(Vec::<Vec<EntityRef>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|list| list.into_iter().map(|e| e.0).collect())
+            .collect())
+    }
+}
+
+/// Saves a PointF field as a pair of floats
+pub mod pointf {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(point: &PointF, serializer: S) -> Result<S::Ok, S::Error> {
+        (point.x, point.y).serialize(serializer)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PointF, D::Error> {
+        let (x, y) = <(f32, f32)>::deserialize(deserializer)?;
+        Ok(PointF::new(x, y))
+    }
+}
+
+/// Every component an entity can have, as written to a save
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SavedEntity {
+    cell: GameCell,
+    player: Option<Player>,
+    unit: Option<Unit>,
+    worker: Option<Worker>,
+    building: Option<Building>,
+    node: Option<ResourceNode>,
+    /// The index of the flow field the cell is following among those in the save
+    flow: Option<usize>,
+}
+
+impl SavedEntity {
+    /// Read the entity from the world, adding the flow field it follows to the flows unless
+    /// another entity already shares it
+    fn new(world: &World, e: Entity, flows: &mut Vec<Arc<FlowField>>) -> Option<Self> {
+        let entry = world.entry_ref(e).ok()?;
+        let cell = entry.get_component::<GameCell>().ok()?;
+        let flow =
+            cell.flow().map(
+                |flow| match flows.iter().position(|other| Arc::ptr_eq(other, flow)) {
+                    Some(index) => index,
+                    None => {
+                        flows.push(flow.clone());
+                        flows.len() - 1
+                    }
+                },
+            );
+        Some(Self {
+            cell: cell.clone(),
+            player: entry.get_component::<Player>().ok().copied(),
+            unit: entry.get_component::<Unit>().ok().cloned(),
+            worker: entry.get_component::<Worker>().ok().cloned(),
+            building: entry.get_component::<Building>().ok().cloned(),
+            node: entry.get_component::<ResourceNode>().ok().cloned(),
+            flow,
+        })
+    }
+
+    /// Add the entity to the world, returning None if it has a set of components no entity in a
+    /// match has
+    fn push(self, world: &mut World) -> Option<Entity> {
+        Some(
+            match (
+                self.player,
+                self.unit,
+                self.worker,
+                self.building,
+                self.node,
+            ) {
+                (Some(player), Some(unit), Some(worker), None, None) => {
+                    world.push((self.cell, unit, player, worker))
+                }
+                (Some(player), Some(unit), None, None, None) => {
+                    world.push((self.cell, unit, player))
+                }
+                (Some(player), None, None, Some(building), None) => {
+                    world.push((self.cell, building, player))
+                }
+                (None, None, None, None, Some(node)) => world.push((self.cell, node)),
+                _ => return None,
+            },
+        )
+    }
+
+    /// Replace the components of an entity already in the world with the saved ones, pointing its
+    /// cell back at the flow field it shares
+    fn overwrite(self, world: &mut World, e: Entity, flows: &[Arc<FlowField>]) {
+        let mut entry = match world.entry_mut(e) {
+            Ok(entry) => entry,
+            Err(_) => return,
+        };
+        if let Ok(cell) = entry.get_component_mut::<GameCell>() {
+            *cell = self.cell;
+            if let Some(flow) = self.flow.and_then(|index| flows.get(index)) {
+                cell.follow_flow(flow.clone());
+            }
+        }
+        if let (Ok(unit), Some(saved)) = (entry.get_component_mut::<Unit>(), self.unit) {
+            *unit = saved;
+        }
+        if let (Ok(worker), Some(saved)) = (entry.get_component_mut::<Worker>(), self.worker) {
+            *worker = saved;
+        }
+        if let (Ok(building), Some(saved)) = (entry.get_component_mut::<Building>(), self.building)
+        {
+            *building = saved;
+        }
+    }
+}
+
+/// The version of a save, read on its own so saves of other versions can be turned away before
+/// the rest of them fails to parse
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+/// A match as written to a save, along with whatever the caller keeps about it outside the
+/// Simulation
+#[derive(Serialize, Deserialize)]
+struct SaveFile<T> {
+    version: u32,
+    /// Every entity in the order the world stores them, which a loaded world keeps so the match
+    /// and its replay carry on as if it had never been saved
+    entities: Vec<SavedEntity>,
+    /// The flow fields being followed, each written once however many cells share it
+    flows: Vec<FlowField>,
+    map: Map,
+    players: Players,
+    time: Time,
+    rng: Rng,
+    brains: Brains,
+    stats: Stats,
+    outcome: Outcome,
+    vision: Vision,
+    /// The orders given since the last tick, which are carried out on the next one
+    orders: Orders,
+    selections: Vec<(usize, Vec<EntityRef>)>,
+    replay: Replay,
+    extra: T,
+}
+
+fn invalid<E: ToString>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// Parse a save with entity references resolving to the given entities, standing in for any
+/// referred to past their end
+fn parse<T: DeserializeOwned>(s: &str, references: Vec<Entity>) -> io::Result<SaveFile<T>> {
+    REFERENCES.with(|r| *r.borrow_mut() = references);
+    let parsed = ron::de::from_str(s).map_err(invalid);
+    REFERENCES.with(|r| r.borrow_mut().clear());
+    parsed
+}
+
+impl Simulation {
+    /// Write the match to a file along with the extra state the caller keeps about it
+    pub fn save<P: AsRef<Path>, T: Serialize>(&self, path: P, extra: &T) -> io::Result<()> {
+        let live = entities(&self.world);
+        REFERENCES.with(|r| *r.borrow_mut() = live.clone());
+
+        let mut selections: Vec<(usize, Vec<EntityRef>)> = self
+            .selections
+            .iter()
+            .map(|(id, selected)| (*id, selected.iter().copied().map(EntityRef).collect()))
+            .collect();
+        selections.sort_by_key(|(id, _)| *id);
+
+        let mut flows = Vec::new();
+        let entities = live
+            .iter()
+            .filter_map(|e| SavedEntity::new(&self.world, *e, &mut flows))
+            .collect();
+        let file = SaveFile {
+            version: SAVE_VERSION,
+            entities,
+            flows: flows.iter().map(|flow| (**flow).clone()).collect(),
+            map: self.resources.get::<Map>().unwrap().clone(),
+            players: self.resources.get::<Players>().unwrap().clone(),
+            time: *self.resources.get::<Time>().unwrap(),
+            rng: self.resources.get::<Rng>().unwrap().clone(),
+            brains: self.resources.get::<Brains>().unwrap().clone(),
+            stats: self.resources.get::<Stats>().unwrap().clone(),
+            outcome: self.resources.get::<Outcome>().unwrap().clone(),
+            vision: self.resources.get::<Vision>().unwrap().clone(),
+            orders: self.resources.get::<Orders>().unwrap().clone(),
+            selections,
+            replay: self.replay(),
+            extra,
+        };
+        let written = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default());
+        REFERENCES.with(|r| r.borrow_mut().clear());
+
+        fs::write(path, written.map_err(invalid)?)
+    }
+
+    /// Read a match written by save along with the extra state saved with it
+    pub fn load<P: AsRef<Path>, T: DeserializeOwned>(path: P) -> io::Result<(Self, T)> {
+        let s = fs::read_to_string(path)?;
+        let version: Version = ron::de::from_str(&s).map_err(invalid)?;
+        if version.version != SAVE_VERSION {
+            return Err(invalid(format!(
+                "save version {} can't be loaded by this version of the game, which loads \
+                 version {}",
+                version.version, SAVE_VERSION
+            )));
+        }
+
+        // the entities referred to only exist once they are added to the world, so the save is
+        // read once to add them and again to point every reference at them
+        let first: SaveFile<T> = parse(&s, Vec::new())?;
+        let mut world = new_world();
+        let mut live = Vec::with_capacity(first.entities.len());
+        for saved in first.entities {
+            live.push(
+                saved
+                    .push(&mut world)
+                    .ok_or_else(|| invalid("an entity has components that don't go together"))?,
+            );
+        }
+
+        let file: SaveFile<T> = parse(&s, live.clone())?;
+        let flows: Vec<Arc<FlowField>> = file.flows.into_iter().map(Arc::new).collect();
+        for (saved, e) in file.entities.into_iter().zip(live.iter()) {
+            saved.overwrite(&mut world, *e, &flows);
+        }
+
+        let mut sim = Simulation::new(file.map, file.players, file.rng.seed());
+        sim.world = world;
+        sim.resources.insert(file.time);
+        sim.resources.insert(file.rng);
+        sim.resources.insert(file.brains);
+        sim.resources.insert(file.stats);
+        sim.resources.insert(file.outcome);
+        sim.resources.insert(file.vision);
+        sim.resources.insert(file.orders);
+        sim.selections = file
+            .selections
+            .into_iter()
+            .map(|(id, selected)| (id, selected.into_iter().map(|e| e.0).collect()))
+            .collect::<HashMap<usize, Vec<Entity>>>();
+        sim.replay = file.replay;
+
+        Ok((sim, file.extra))
+    }
+}
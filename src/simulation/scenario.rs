@@ -0,0 +1,314 @@
+use std::{fs, io, path::Path};
+
+use bracket_lib::prelude::{Point, Rect, RGB};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{
+    BuildingKind, Condition, Map, PlayerInfo, Players, Race, ResourceKind, Tile, UnitKind,
+};
+
+/// The scenario built into the game, played when no other is chosen
+const SKIRMISH: &str = include_str!("../../scenarios/skirmish.ron");
+
+/// A rectangle of the map set to one kind of tile
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TileFill {
+    pub tile: Tile,
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+/// The size of a scenario's map and the terrain on it, laid over plain ground in order
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MapDef {
+    pub width: i32,
+    pub height: i32,
+    #[serde(default)]
+    pub tiles: Vec<TileFill>,
+}
+
+/// A player in a scenario and what they start the match with
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayerDef {
+    pub name: String,
+    pub race: Race,
+    pub team: usize,
+    pub color: (u8, u8, u8),
+    #[serde(default)]
+    pub minerals: u32,
+    #[serde(default)]
+    pub gas: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// Units of one kind a player starts with, all at the same point
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UnitPlacement {
+    pub kind: UnitKind,
+    pub player: usize,
+    pub x: i32,
+    pub y: i32,
+    #[serde(default = "one")]
+    pub count: u32,
+}
+
+/// A finished building a player starts with, placed by its top left corner
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BuildingPlacement {
+    pub kind: BuildingKind,
+    pub player: usize,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A resource node on the map
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NodePlacement {
+    pub kind: ResourceKind,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Everything needed to set up a match: the map, the players, what each starts with, and how the
+/// match is won
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub map: MapDef,
+    #[serde(default)]
+    pub local: usize,
+    pub players: Vec<PlayerDef>,
+    #[serde(default)]
+    pub units: Vec<UnitPlacement>,
+    #[serde(default)]
+    pub buildings: Vec<BuildingPlacement>,
+    #[serde(default)]
+    pub nodes: Vec<NodePlacement>,
+    #[serde(default)]
+    pub victory: Vec<Condition>,
+}
+
+impl Scenario {
+    /// Return the standard three player skirmish of Bionic against Bug and Alien
+    pub fn skirmish() -> Self {
+        Self::parse(SKIRMISH).expect("the built-in skirmish scenario is valid")
+    }
+
+    /// Parse and validate a scenario, naming what is wrong with it if it can't be played
+    pub fn parse(s: &str) -> io::Result<Self> {
+        let scenario: Scenario = ron::de::from_str(s)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        scenario
+            .validate()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(scenario)
+    }
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Read every scenario in the directory, sorted by name, along with the errors of any that
+    /// couldn't be read and the files they came from
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> (Vec<Self>, Vec<(String, io::Error)>) {
+        let mut scenarios = Vec::new();
+        let mut errors = Vec::new();
+        if let Ok(entries) = fs::read_dir(dir) {
+            for path in entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+            {
+                if path.extension().map_or(false, |ext| ext == "ron") {
+                    match Self::load(&path) {
+                        Ok(scenario) => scenarios.push(scenario),
+                        Err(e) => errors.push((path.display().to_string(), e)),
+                    }
+                }
+            }
+        }
+        scenarios.sort_by(|a, b| a.name.cmp(&b.name));
+        (scenarios, errors)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.map.width <= 0 || self.map.height <= 0 {
+            return Err(format!(
+                "the map is {}x{}, but must be at least 1x1",
+                self.map.width, self.map.height
+            ));
+        }
+        if self.players.is_empty() {
+            return Err("there are no players".to_string());
+        }
+        if self.local >= self.players.len() {
+            return Err(format!(
+                "the local player is {}, but there are only {} players",
+                self.local,
+                self.players.len()
+            ));
+        }
+
+        let map = self.map();
+        let check = |what: String, player: Option<usize>, point: Point| {
+            if let Some(player) = player {
+                if player >= self.players.len() {
+                    return Err(format!(
+                        "the {} belongs to player {}, but there are only {} players",
+                        what,
+                        player,
+                        self.players.len()
+                    ));
+                }
+            }
+            if map.idx(point).is_none() {
+                return Err(format!(
+                    "the {} at ({}, {}) is off the map",
+                    what, point.x, point.y
+                ));
+            }
+            Ok(())
+        };
+        for unit in self.units.iter() {
+            check(
+                unit.kind.name().to_string(),
+                Some(unit.player),
+                Point::new(unit.x, unit.y),
+            )?;
+        }
+        for building in self.buildings.iter() {
+            let (w, h) = building.kind.size();
+            check(
+                building.kind.name().to_string(),
+                Some(building.player),
+                Point::new(building.x, building.y),
+            )?;
+            check(
+                building.kind.name().to_string(),
+                None,
+                Point::new(building.x + w - 1, building.y + h - 1),
+            )?;
+        }
+        for node in self.nodes.iter() {
+            check(
+                format!("{:?} node", node.kind),
+                None,
+                Point::new(node.x, node.y),
+            )?;
+        }
+
+        // buildings and nodes need open ground to themselves, and units can't start inside them
+        let mut map = map;
+        let mut place = |what: String, rect: Rect| {
+            if map.is_clear(rect) {
+                map.block(rect);
+                Ok(())
+            } else {
+                Err(format!(
+                    "the {} at ({}, {}) is on ground that can't be walked on or overlaps something \
+                     placed before it",
+                    what, rect.x1, rect.y1
+                ))
+            }
+        };
+        for building in self.buildings.iter() {
+            let (w, h) = building.kind.size();
+            place(
+                building.kind.name().to_string(),
+                Rect::with_size(building.x, building.y, w, h),
+            )?;
+        }
+        for node in self.nodes.iter() {
+            place(
+                format!("{:?} node", node.kind),
+                Rect::with_size(node.x, node.y, 1, 1),
+            )?;
+        }
+        for unit in self.units.iter() {
+            if !map.is_walkable(Point::new(unit.x, unit.y)) {
+                return Err(format!(
+                    "the {} at ({}, {}) is on ground that can't be walked on or inside a building \
+                     or node",
+                    unit.kind.name(),
+                    unit.x,
+                    unit.y
+                ));
+            }
+        }
+
+        for condition in self.victory.iter() {
+            match condition {
+                Condition::Survive { team, .. }
+                    if self.players.iter().all(|player| player.team != *team) =>
+                {
+                    return Err(format!(
+                        "team {} has to survive, but no player is on it",
+                        team
+                    ))
+                }
+                Condition::Survive { seconds, .. } if !(*seconds > 0.0) => {
+                    return Err(format!(
+                        "the time to survive is {} seconds, but must be more than 0",
+                        seconds
+                    ))
+                }
+                Condition::HoldPoint { w, h, .. } if *w <= 0 || *h <= 0 => {
+                    return Err(format!(
+                        "the point to hold is {}x{}, but must be at least 1x1",
+                        w, h
+                    ))
+                }
+                Condition::HoldPoint { x, y, w, h, .. }
+                    if map.idx(Point::new(*x, *y)).is_none()
+                        || map.idx(Point::new(x + w - 1, y + h - 1)).is_none() =>
+                {
+                    return Err(format!(
+                        "the point to hold at ({}, {}) is off the map",
+                        x, y
+                    ))
+                }
+                Condition::HoldPoint { seconds, .. } if !(*seconds > 0.0) => {
+                    return Err(format!(
+                        "the time to hold the point is {} seconds, but must be more than 0",
+                        seconds
+                    ))
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the scenario's map with its terrain filled in
+    pub fn map(&self) -> Map {
+        let mut map = Map::new(self.map.width, self.map.height);
+        for fill in self.map.tiles.iter() {
+            map.fill(Rect::with_size(fill.x, fill.y, fill.w, fill.h), fill.tile);
+        }
+        map
+    }
+
+    /// Return the scenario's players with their starting resources
+    pub fn players(&self) -> Players {
+        self.players
+            .iter()
+            .fold(Players::new(self.local), |players, player| {
+                let (r, g, b) = player.color;
+                players.with_player(
+                    PlayerInfo::new(
+                        &player.name,
+                        player.race,
+                        player.team,
+                        RGB::from_u8(r, g, b),
+                    )
+                    .with_resources(player.minerals, player.gas),
+                )
+            })
+    }
+}
@@ -0,0 +1,937 @@
+use std::{collections::HashMap, sync::Arc};
+
+use bracket_lib::prelude::*;
+
+use legion::{storage::Component, systems::ParallelRunnable, world::SubWorld, *};
+
+use super::{building, unit};
+use crate::{
+    components::{Building, GameCell, Player, Rally, ResourceNode, Task, Unit, Worker, QUEUE_SIZE},
+    types::{
+        Condition, Effect, Effects, FlowField, Map, Mode, Order, Orders, Outcome, Players,
+        ResourceKind, Rng, Stats, Time, Vision,
+    },
+};
+
+/// The number of cells in an order at which a shared FlowField is used instead of A*
+const FLOW_GROUP_SIZE: usize = 8;
+
+/// The fraction of the overlap between two cells that is resolved each tick
+const SEPARATION_STRENGTH: f32 = 0.5;
+/// The furthest a cell can be pushed in one tick
+const MAX_PUSH: f32 = 0.25;
+
+/// The amount of a resource a worker carries back to a depot in one trip
+const LOAD_SIZE: u32 = 5;
+/// How far a worker looks for another node of the same resource once its node is depleted
+const NODE_SEARCH_RADIUS: i32 = 10;
+
+/// Carry out the orders issued since the last tick, ignoring any given to entities that no
+/// longer exist or that the issuing player does not own
+pub fn apply_orders() -> impl ParallelRunnable {
+    SystemBuilder::new("apply_orders")
+        .write_resource::<Orders>()
+        .write_resource::<Map>()
+        .write_resource::<Players>()
+        .with_query(<(Read<GameCell>,)>::query().filter(component::<Unit>()))
+        .read_component::<Player>()
+        .read_component::<ResourceNode>()
+        .write_component::<Unit>()
+        .write_component::<GameCell>()
+        .write_component::<Worker>()
+        .write_component::<Building>()
+        .build(|commands, world, (orders, map, players), unit_query| {
+            for (player, order) in orders.drain() {
+                match order {
+                    Order::Move(units, target, mode) => {
+                        move_units(world, map, player, &units, target, mode)
+                    }
+                    Order::Attack(units, target) => {
+                        let target_cell = world.entry_ref(target).ok().and_then(|entry| {
+                            match (
+                                entry.get_component::<GameCell>(),
+                                entry.get_component::<Player>(),
+                            ) {
+                                (Ok(cell), Ok(owner)) if players.hostile(player, *owner) => {
+                                    Some(cell.clone())
+                                }
+                                _ => None,
+                            }
+                        });
+                        if let Some(target_cell) = target_cell {
+                            for e in owned::<Unit>(world, player, &units) {
+                                let mut entry = world.entry_mut(e).unwrap();
+                                if let Ok(worker) = entry.get_component_mut::<Worker>() {
+                                    worker.idle();
+                                }
+                                let range = match entry.get_component_mut::<Unit>() {
+                                    Ok(unit) => {
+                                        unit.set_focus(Some(target));
+                                        unit.range()
+                                    }
+                                    Err(_) => continue,
+                                };
+                                if let Ok(cell) = entry.get_component_mut::<GameCell>() {
+                                    // units already in range stand and fire instead of closing in
+                                    if cell
+                                        .range_rect(range)
+                                        .point_in_rect(target_cell.nearest_point(cell.point()))
+                                    {
+                                        cell.stop();
+                                    } else {
+                                        cell.follow(target);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    Order::Stop(units) => {
+                        for e in owned::<Unit>(world, player, &units) {
+                            let mut entry = world.entry_mut(e).unwrap();
+                            if let Ok(worker) = entry.get_component_mut::<Worker>() {
+                                worker.idle();
+                            }
+                            if let Ok(unit) = entry.get_component_mut::<Unit>() {
+                                unit.set_focus(None);
+                            }
+                            if let Ok(cell) = entry.get_component_mut::<GameCell>() {
+                                cell.stop();
+                            }
+                        }
+                    }
+                    Order::Hold(units) => {
+                        for e in owned::<Unit>(world, player, &units) {
+                            let mut entry = world.entry_mut(e).unwrap();
+                            if let Ok(worker) = entry.get_component_mut::<Worker>() {
+                                worker.idle();
+                            }
+                            if let Ok(unit) = entry.get_component_mut::<Unit>() {
+                                unit.set_focus(None);
+                            }
+                            if let Ok(cell) = entry.get_component_mut::<GameCell>() {
+                                cell.hold();
+                            }
+                        }
+                    }
+                    Order::Gather(units, node) => {
+                        let target = world.entry_ref(node).ok().and_then(|entry| {
+                            match (
+                                entry.get_component::<GameCell>(),
+                                entry.get_component::<ResourceNode>(),
+                            ) {
+                                (Ok(cell), Ok(resource)) => Some((cell.point(), resource.kind())),
+                                _ => None,
+                            }
+                        });
+                        if let Some((point, kind)) = target {
+                            for e in owned::<Worker>(world, player, &units) {
+                                if let Ok(worker) =
+                                    world.entry_mut(e).unwrap().get_component_mut::<Worker>()
+                                {
+                                    worker.gather(node, point, kind);
+                                }
+                            }
+                        }
+                    }
+                    Order::Build(kind, origin) => {
+                        let (w, h) = kind.size();
+                        let footprint = Rect::with_size(origin.x, origin.y, w, h);
+                        let clear = map.is_clear(footprint)
+                            && !unit_query
+                                .iter(world)
+                                .any(|(cell,)| footprint.point_in_rect(cell.point()));
+                        if let Some(info) = players.get_mut(player) {
+                            if clear
+                                && info.race().buildings().contains(&kind)
+                                && info.spend(kind.cost())
+                            {
+                                map.block(footprint);
+                                commands.push(building(
+                                    Building::site(kind),
+                                    origin.x,
+                                    origin.y,
+                                    player,
+                                    info.color(),
+                                ));
+                            }
+                        }
+                    }
+                    Order::Train(e, kind) => {
+                        if owned::<Building>(world, player, &[e]).is_empty() {
+                            continue;
+                        }
+                        let info = match players.get_mut(player) {
+                            Some(info) => info,
+                            None => continue,
+                        };
+                        if let Ok(building) =
+                            world.entry_mut(e).unwrap().get_component_mut::<Building>()
+                        {
+                            if building.is_constructed()
+                                && building.queue().len() < QUEUE_SIZE
+                                && building.kind().trains().contains(&kind)
+                                && info.has_supply(kind.supply())
+                                && info.spend(kind.cost())
+                            {
+                                building.queue_unit(kind);
+                            }
+                        }
+                    }
+                    Order::Cancel(e) => {
+                        for e in owned::<Building>(world, player, &[e]) {
+                            if let Ok(building) =
+                                world.entry_mut(e).unwrap().get_component_mut::<Building>()
+                            {
+                                if let (Some(kind), Some(info)) =
+                                    (building.cancel(), players.get_mut(player))
+                                {
+                                    info.refund(kind.cost());
+                                }
+                            }
+                        }
+                    }
+                    Order::Rally(buildings, rally) => {
+                        for e in owned::<Building>(world, player, &buildings) {
+                            if let Ok(building) =
+                                world.entry_mut(e).unwrap().get_component_mut::<Building>()
+                            {
+                                if !building.kind().trains().is_empty() {
+                                    building.set_rally(rally);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        })
+}
+
+/// Return the entities from the list that still exist, have the given component, and belong to
+/// the given player
+fn owned<T: Component>(world: &SubWorld, player: Player, entities: &[Entity]) -> Vec<Entity> {
+    entities
+        .iter()
+        .copied()
+        .filter(|e| {
+            world.entry_ref(*e).map_or(false, |entry| {
+                entry.get_component::<T>().is_ok()
+                    && entry.get_component::<Player>().ok() == Some(&player)
+            })
+        })
+        .collect()
+}
+
+/// Send the units to points spread around the target, with the closest units taking the
+/// innermost points so the group fills in around it
+fn move_units(
+    world: &mut SubWorld,
+    map: &Map,
+    player: Player,
+    units: &[Entity],
+    target: Point,
+    mode: Mode,
+) {
+    let mut units: Vec<(Entity, Point)> = owned::<Unit>(world, player, units)
+        .into_iter()
+        .filter_map(|e| {
+            let entry = world.entry_ref(e).ok()?;
+            let point = entry.get_component::<GameCell>().ok()?.point();
+            Some((e, point))
+        })
+        .collect();
+    units
+        .sort_by_key(|(_, point)| DistanceAlg::PythagorasSquared.distance2d(*point, target) as i32);
+
+    let slots = map.spread(target, units.len());
+    let flow = if units.len() >= FLOW_GROUP_SIZE {
+        Some(Arc::new(FlowField::new(map, &slots)))
+    } else {
+        None
+    };
+
+    for (i, (e, _)) in units.iter().enumerate() {
+        let mut entry = world.entry_mut(*e).unwrap();
+        if let Ok(worker) = entry.get_component_mut::<Worker>() {
+            worker.idle();
+        }
+        if let Ok(unit) = entry.get_component_mut::<Unit>() {
+            unit.set_focus(None);
+        }
+        if let Ok(cell) = entry.get_component_mut::<GameCell>() {
+            cell.move_pos(slots.get(i).copied().unwrap_or(target), mode);
+            if let Some(flow) = &flow {
+                cell.follow_flow(Arc::clone(flow));
+            }
+        }
+    }
+}
+
+/// Move every unit along its path and advance its attack timer
+pub fn update_units() -> impl ParallelRunnable {
+    SystemBuilder::new("update_units")
+        .read_resource::<Time>()
+        .read_resource::<Map>()
+        .with_query(<(Write<GameCell>, Write<Unit>)>::query())
+        .build(|_, world, (time, map), query| {
+            for (cell, unit) in query.iter_mut(world) {
+                cell.update(time.dt(), unit.speed(), map);
+                unit.tic(time.dt());
+            }
+        })
+}
+
+/// Advance construction and production in every building, sending each trained unit out of the
+/// middle of the building's bottom edge towards its rally. Production pauses while the owner is
+/// over their supply cap.
+pub fn produce_units() -> impl ParallelRunnable {
+    SystemBuilder::new("produce_units")
+        .read_resource::<Time>()
+        .read_resource::<Map>()
+        .read_resource::<Players>()
+        .write_resource::<Stats>()
+        .with_query(<(Write<GameCell>, Write<Building>, Read<Player>)>::query())
+        .build(|commands, world, (time, map, players, stats), query| {
+            for (cell, building, player) in query.iter_mut(world) {
+                cell.update(time.dt(), 0.0, map);
+                building.construct(time.dt());
+                if !players
+                    .get(*player)
+                    .map_or(false, |info| info.has_supply(0))
+                {
+                    continue;
+                }
+
+                let kind = match building.train(time.dt()) {
+                    Some(kind) => kind,
+                    None => continue,
+                };
+                let footprint = cell.footprint();
+                let exit = Point::new(footprint.x1 + footprint.width() / 2, footprint.y2);
+                if let Some(point) = map.nearest_walkable(exit) {
+                    let (mut spawned, unit_stats, owner) = unit(kind, point.x, point.y, *player);
+                    match building.rally() {
+                        Some(Rally::Point(target, mode)) => spawned.move_pos(target, mode),
+                        Some(Rally::Unit(target)) => spawned.follow(target),
+                        None => (),
+                    }
+                    stats.add_produced(owner);
+                    if kind.is_worker() {
+                        commands.push((spawned, unit_stats, owner, Worker::new()));
+                    } else {
+                        commands.push((spawned, unit_stats, owner));
+                    }
+                }
+            }
+        })
+}
+
+/// Push apart units whose cells overlap, treating each cell as a 1x1 box and resolving along
+/// the axis of least overlap. Holding units are never pushed and cells slide along impassable
+/// tiles instead of entering them.
+pub fn separate_units() -> impl ParallelRunnable {
+    SystemBuilder::new("separate_units")
+        .read_resource::<Map>()
+        .write_resource::<Rng>()
+        .with_query(<(Read<GameCell>,)>::query().filter(component::<Unit>()))
+        .write_component::<GameCell>()
+        .build(|_, world, (map, rng), query| {
+            let mut cells = Vec::new();
+            for chunk in query.iter_chunks(world) {
+                for (e, (cell,)) in chunk.into_iter_entities() {
+                    cells.push((e, cell.position(), cell.is_holding()));
+                }
+            }
+
+            let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+            for (i, (_, point, _)) in cells.iter().enumerate() {
+                grid.entry((point.x.floor() as i32, point.y.floor() as i32))
+                    .or_insert_with(Vec::new)
+                    .push(i);
+            }
+
+            let mut pushes = vec![(0.0, 0.0); cells.len()];
+            for (i, (_, a, a_holding)) in cells.iter().enumerate() {
+                let (gx, gy) = (a.x.floor() as i32, a.y.floor() as i32);
+                for nx in gx - 1..=gx + 1 {
+                    for ny in gy - 1..=gy + 1 {
+                        let others = match grid.get(&(nx, ny)) {
+                            Some(others) => others,
+                            None => continue,
+                        };
+                        for j in others.iter().copied().filter(|j| *j > i) {
+                            let (_, b, b_holding) = &cells[j];
+                            let dx = b.x - a.x;
+                            let dy = b.y - a.y;
+                            if dx.abs() >= 1.0 || dy.abs() >= 1.0 {
+                                continue;
+                            }
+
+                            let (a_share, b_share) = match (a_holding, b_holding) {
+                                (true, true) => continue,
+                                (true, false) => (0.0, 1.0),
+                                (false, true) => (1.0, 0.0),
+                                (false, false) => (0.5, 0.5),
+                            };
+
+                            // cells overlapping as much along both axes are split along a random one
+                            let along_x = if dx.abs() == dy.abs() {
+                                rng.range(0, 2) == 0
+                            } else {
+                                dx.abs() > dy.abs()
+                            };
+                            let (push_x, push_y) = if along_x {
+                                (-(1.0 - dx.abs()) * direction(dx), 0.0)
+                            } else {
+                                (0.0, -(1.0 - dy.abs()) * direction(dy))
+                            };
+
+                            pushes[i].0 += push_x * a_share * SEPARATION_STRENGTH;
+                            pushes[i].1 += push_y * a_share * SEPARATION_STRENGTH;
+                            pushes[j].0 -= push_x * b_share * SEPARATION_STRENGTH;
+                            pushes[j].1 -= push_y * b_share * SEPARATION_STRENGTH;
+                        }
+                    }
+                }
+            }
+
+            for ((e, _, _), (x, y)) in cells.iter().zip(pushes.iter()) {
+                if *x != 0.0 || *y != 0.0 {
+                    if let Ok(cell) = world.entry_mut(*e).unwrap().get_component_mut::<GameCell>() {
+                        cell.nudge(
+                            x.max(-MAX_PUSH).min(MAX_PUSH),
+                            y.max(-MAX_PUSH).min(MAX_PUSH),
+                            map,
+                        );
+                    }
+                }
+            }
+        })
+}
+
+/// Return the sign of the given difference, treating zero as positive
+fn direction(d: f32) -> f32 {
+    if d >= 0.0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+/// Keep units that are following another entity heading towards it, and stop them once it is
+/// gone
+pub fn follow_units() -> impl ParallelRunnable {
+    SystemBuilder::new("follow_units")
+        .with_query(<(Read<GameCell>, Read<Unit>)>::query())
+        .write_component::<GameCell>()
+        .build(|_, world, _, query| {
+            let mut following = Vec::new();
+            for chunk in query.iter_chunks(world) {
+                for (e, (cell, unit)) in chunk.into_iter_entities() {
+                    if let Some(target) = cell.following() {
+                        // a unit chasing its focus only needs to get within range of it
+                        let range = if unit.focus() == Some(target) {
+                            unit.range()
+                        } else {
+                            0
+                        };
+                        following.push((e, target, cell.point(), range));
+                    }
+                }
+            }
+            for (e, target, from, range) in following.iter() {
+                let point = world.entry_ref(*target).ok().and_then(|entry| {
+                    entry
+                        .get_component::<GameCell>()
+                        .ok()
+                        .map(|cell| cell.nearest_point(*from))
+                });
+                if let Ok(cell) = world.entry_mut(*e).unwrap().get_component_mut::<GameCell>() {
+                    match point {
+                        Some(point) => cell.chase(point, *range),
+                        None => cell.stop(),
+                    }
+                }
+            }
+        })
+}
+
+pub fn attack_units() -> impl ParallelRunnable {
+    SystemBuilder::new("attack_units")
+        .read_resource::<Players>()
+        .write_resource::<Stats>()
+        .write_resource::<Effects>()
+        .with_query(<(Read<GameCell>, Read<Unit>, Read<Player>)>::query())
+        .with_query(<(Read<GameCell>, Read<Unit>, Read<Player>)>::query())
+        .with_query(<(Read<GameCell>, Read<Unit>, Read<Player>)>::query())
+        .with_query(<(Read<GameCell>, Read<Building>, Read<Player>)>::query())
+        .write_component::<GameCell>()
+        .write_component::<Unit>()
+        .write_component::<Building>()
+        .build(
+            |_,
+             world,
+             (players, stats, effects),
+             (query, attack_query, moving_query, building_query)| {
+                let mut attacking_units = Vec::new();
+                let mut moving_units = Vec::new();
+                for chunk in query.iter_chunks(world) {
+                    for (e, (cell, unit, player)) in chunk.into_iter_entities() {
+                        // a focused target in range is attacked ahead of anything else
+                        let focus = unit.focus().filter(|target| {
+                            world.entry_ref(*target).map_or(false, |entry| {
+                                entry.get_component::<GameCell>().map_or(false, |cell2| {
+                                    cell.range_rect(unit.range())
+                                        .point_in_rect(cell2.nearest_point(cell.point()))
+                                })
+                            })
+                        });
+                        let mut attacked = false;
+                        if let Some(target) = focus {
+                            attacking_units.push((e, target, unit.attack(), cell.point(), *player));
+                            attacked = true;
+                        }
+                        for attack_chunk in attack_query.iter_chunks(world) {
+                            if attacked {
+                                break;
+                            }
+                            for (e2, (cell2, _, player2)) in attack_chunk.into_iter_entities() {
+                                if players.hostile(*player, *player2)
+                                    && cell.range_rect(unit.range()).point_in_rect(cell2.point())
+                                {
+                                    attacking_units.push((
+                                        e,
+                                        e2,
+                                        unit.attack(),
+                                        cell.point(),
+                                        *player,
+                                    ));
+                                    attacked = true;
+                                    break;
+                                }
+                            }
+                        }
+                        if !attacked {
+                            for building_chunk in building_query.iter_chunks(world) {
+                                for (e2, (cell2, _, player2)) in building_chunk.into_iter_entities()
+                                {
+                                    if players.hostile(*player, *player2)
+                                        && cell
+                                            .range_rect(unit.range())
+                                            .point_in_rect(cell2.nearest_point(cell.point()))
+                                    {
+                                        attacking_units.push((
+                                            e,
+                                            e2,
+                                            unit.attack(),
+                                            cell.point(),
+                                            *player,
+                                        ));
+                                        attacked = true;
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                        if !attacked && !cell.is_holding() {
+                            for (cell2, _, player2) in moving_query.iter(world) {
+                                if players.hostile(*player, *player2)
+                                    && cell
+                                        .range_rect(unit.follow_dist())
+                                        .point_in_rect(cell2.point())
+                                {
+                                    moving_units.push((e, cell2.point()));
+                                    attacked = true;
+                                    break;
+                                }
+                            }
+                        }
+                        if !attacked && !cell.is_holding() {
+                            for (cell2, _, player2) in building_query.iter(world) {
+                                let target = cell2.nearest_point(cell.point());
+                                if players.hostile(*player, *player2)
+                                    && cell.range_rect(unit.follow_dist()).point_in_rect(target)
+                                {
+                                    moving_units.push((e, target));
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+                for (e, e2, attack, pt, owner) in attacking_units.iter() {
+                    let is_moving = if let Ok(cell) =
+                        world.entry_mut(*e).unwrap().get_component_mut::<GameCell>()
+                    {
+                        if !cell.is_moving() {
+                            cell.stop_moving();
+                            false
+                        } else {
+                            true
+                        }
+                    } else {
+                        true
+                    };
+
+                    if !is_moving {
+                        if let Some(damage) = *attack {
+                            if let Ok(unit) =
+                                world.entry_mut(*e).unwrap().get_component_mut::<Unit>()
+                            {
+                                unit.reset_tic();
+                            }
+                            stats.add_damage(*owner, damage);
+
+                            let mut entry = world.entry_mut(*e2).unwrap();
+                            let is_unit = entry.get_component::<Unit>().is_ok();
+                            if let Ok(cell2) = entry.get_component_mut::<GameCell>() {
+                                cell2.set_harmed();
+                                effects.push(Effect::Damage(cell2.nearest_point(*pt), damage));
+                                if is_unit {
+                                    cell2.move_to_attacker(*pt);
+                                }
+                            }
+                            if let Ok(unit2) = entry.get_component_mut::<Unit>() {
+                                unit2.harm(damage);
+                            }
+                            if let Ok(building2) = entry.get_component_mut::<Building>() {
+                                building2.harm(damage);
+                            }
+                        }
+                    }
+                }
+                for (e, pt2) in moving_units.iter() {
+                    if let Ok(cell) = world.entry_mut(*e).unwrap().get_component_mut::<GameCell>() {
+                        cell.move_towards(*pt2);
+                    }
+                }
+            },
+        )
+}
+
+/// Run each worker's gather/return cycle: walk to its node, mine a load, carry it to the
+/// nearest of its owner's depots, and head back. Depleted nodes are removed and workers move on
+/// to the closest node of the same resource nearby.
+pub fn gather_resources() -> impl ParallelRunnable {
+    SystemBuilder::new("gather_resources")
+        .read_resource::<Time>()
+        .write_resource::<Map>()
+        .write_resource::<Players>()
+        .with_query(<(Read<GameCell>, Read<Worker>, Read<Player>)>::query())
+        .with_query(<(Read<GameCell>, Read<ResourceNode>)>::query())
+        .with_query(<(Read<GameCell>, Read<Building>, Read<Player>)>::query())
+        .write_component::<GameCell>()
+        .write_component::<Worker>()
+        .write_component::<ResourceNode>()
+        .build(
+            |commands, world, (time, map, players), (worker_query, node_query, depot_query)| {
+                let mut nodes: Vec<(Entity, Point, ResourceKind)> = Vec::new();
+                for chunk in node_query.iter_chunks(world) {
+                    for (e, (cell, node)) in chunk.into_iter_entities() {
+                        if !node.is_depleted() {
+                            nodes.push((e, cell.point(), node.kind()));
+                        }
+                    }
+                }
+                let depots: Vec<(GameCell, Player)> = depot_query
+                    .iter(world)
+                    .filter(|(_, building, _)| {
+                        building.is_constructed() && building.kind().is_depot()
+                    })
+                    .map(|(cell, _, player)| (cell.clone(), *player))
+                    .collect();
+
+                let mut workers = Vec::new();
+                for chunk in worker_query.iter_chunks(world) {
+                    for (e, (cell, worker, player)) in chunk.into_iter_entities() {
+                        if worker.task() != Task::Idle {
+                            workers.push((
+                                e,
+                                cell.point(),
+                                cell.has_destination(),
+                                worker.clone(),
+                                *player,
+                            ));
+                        }
+                    }
+                }
+
+                for (e, point, moving, mut worker, player) in workers {
+                    let mut order = None;
+                    match worker.task() {
+                        Task::Gather => {
+                            let node = worker.node().and_then(|(node, last, kind)| {
+                                nodes.iter().find(|(e, _, _)| *e == node).or_else(|| {
+                                    nodes
+                                        .iter()
+                                        .filter(|(_, p, k)| {
+                                            *k == kind
+                                                && (p.x - last.x).abs() <= NODE_SEARCH_RADIUS
+                                                && (p.y - last.y).abs() <= NODE_SEARCH_RADIUS
+                                        })
+                                        .min_by_key(|(_, p, _)| {
+                                            DistanceAlg::PythagorasSquared.distance2d(*p, last)
+                                                as i32
+                                        })
+                                })
+                            });
+                            match node.copied() {
+                                Some((node, node_point, kind)) => {
+                                    if worker.node().map(|(e, _, _)| e) != Some(node) {
+                                        worker.gather(node, node_point, kind);
+                                    }
+                                    if !adjacent(point, node_point) {
+                                        if !moving {
+                                            order = Some(node_point);
+                                        }
+                                    } else if !moving && worker.mine(time.dt()) {
+                                        let mut entry = world.entry_mut(node).unwrap();
+                                        if let Ok(resource) =
+                                            entry.get_component_mut::<ResourceNode>()
+                                        {
+                                            worker.load(kind, resource.take(LOAD_SIZE));
+                                            if resource.is_depleted() {
+                                                map.unblock(Rect::with_size(
+                                                    node_point.x,
+                                                    node_point.y,
+                                                    1,
+                                                    1,
+                                                ));
+                                                nodes.retain(|(e, _, _)| *e != node);
+                                                commands.remove(node);
+                                            }
+                                        }
+                                        worker.set_task(Task::Return);
+                                    }
+                                }
+                                None => worker.idle(),
+                            }
+                        }
+                        Task::Return => {
+                            let depot = depots
+                                .iter()
+                                .filter(|(_, owner)| *owner == player)
+                                .map(|(cell, _)| cell.nearest_point(point))
+                                .min_by_key(|target| {
+                                    DistanceAlg::PythagorasSquared.distance2d(*target, point) as i32
+                                });
+                            if let Some(target) = depot {
+                                if adjacent(point, target) {
+                                    if let Some((kind, x)) = worker.unload() {
+                                        if let Some(info) = players.get_mut(player) {
+                                            info.earn(kind, x);
+                                        }
+                                    }
+                                    worker.set_task(Task::Gather);
+                                    order = worker.node().map(|(_, node_point, _)| node_point);
+                                } else if !moving {
+                                    order = Some(target);
+                                }
+                            }
+                        }
+                        Task::Idle => (),
+                    }
+
+                    let mut entry = world.entry_mut(e).unwrap();
+                    if let Ok(w) = entry.get_component_mut::<Worker>() {
+                        *w = worker;
+                    }
+                    if let Some(target) = order {
+                        if let Ok(cell) = entry.get_component_mut::<GameCell>() {
+                            cell.move_pos(target, Mode::Move);
+                        }
+                    }
+                }
+            },
+        )
+}
+
+/// Return whether the two points are the same or next to each other
+fn adjacent(a: Point, b: Point) -> bool {
+    (a.x - b.x).abs() <= 1 && (a.y - b.y).abs() <= 1
+}
+
+/// Recount the supply used by each player's units and queued production, and the supply cap
+/// provided by their constructed buildings
+pub fn count_supply() -> impl ParallelRunnable {
+    SystemBuilder::new("count_supply")
+        .write_resource::<Players>()
+        .with_query(<(Read<Unit>, Read<Player>)>::query())
+        .with_query(<(Read<Building>, Read<Player>)>::query())
+        .build(|_, world, players, (unit_query, building_query)| {
+            players.reset_supply();
+            for (unit, player) in unit_query.iter(world) {
+                players.add_supply(*player, unit.kind().supply(), 0);
+            }
+            for (building, player) in building_query.iter(world) {
+                let queued = building.queue().iter().map(|kind| kind.supply()).sum();
+                let cap = if building.is_constructed() {
+                    building.kind().supply()
+                } else {
+                    0
+                };
+                players.add_supply(*player, queued, cap);
+            }
+        })
+}
+
+pub fn clear_units() -> impl ParallelRunnable {
+    SystemBuilder::new("clear_units")
+        .write_resource::<Map>()
+        .write_resource::<Stats>()
+        .write_resource::<Effects>()
+        .with_query(
+            <(Read<GameCell>, Read<Unit>, Read<Player>)>::query().filter(maybe_changed::<Unit>()),
+        )
+        .with_query(<(Read<GameCell>, Read<Building>)>::query().filter(maybe_changed::<Building>()))
+        .write_component::<Unit>()
+        .write_component::<Building>()
+        .build(
+            |commands, world, (map, stats, effects), (query, building_query)| {
+                let mut deleted = Vec::new();
+                for chunk in query.iter_chunks(world) {
+                    for (e, (cell, unit, player)) in chunk.into_iter_entities() {
+                        if unit.hp() <= 0 {
+                            stats.add_lost(*player);
+                            effects.push(Effect::Death(
+                                cell.point(),
+                                unit.kind().symbol(),
+                                unit.kind().color(),
+                            ));
+                            deleted.push(e);
+                        }
+                    }
+                }
+                for chunk in building_query.iter_chunks(world) {
+                    for (e, (cell, building)) in chunk.into_iter_entities() {
+                        if building.hp() <= 0 {
+                            map.unblock(cell.footprint());
+                            deleted.push(e);
+                        }
+                    }
+                }
+                for e in deleted.iter() {
+                    commands.remove(*e);
+                }
+            },
+        )
+}
+
+/// End the match once a team meets one of its victory conditions: being the last team with units
+/// or buildings left, holding an area alone for long enough, or surviving until the timer runs out
+pub fn check_victory() -> impl ParallelRunnable {
+    SystemBuilder::new("check_victory")
+        .read_resource::<Time>()
+        .read_resource::<Players>()
+        .write_resource::<Outcome>()
+        .with_query(<(Read<GameCell>, Read<Unit>, Read<Player>)>::query())
+        .with_query(<(Read<Building>, Read<Player>)>::query())
+        .build(
+            |_, world, (time, players, outcome), (unit_query, building_query)| {
+                if outcome.is_finished() {
+                    return;
+                }
+                let team = |player: &Player| players.get(*player).map(|info| info.team());
+
+                let mut alive: Vec<usize> = unit_query
+                    .iter(world)
+                    .filter_map(|(_, _, player)| team(player))
+                    .chain(
+                        building_query
+                            .iter(world)
+                            .filter_map(|(_, player)| team(player)),
+                    )
+                    .collect();
+                alive.sort_unstable();
+                alive.dedup();
+                let elapsed = time.tick() as f32 * time.dt();
+
+                let mut winners = None;
+                for (i, condition) in outcome.conditions().to_vec().iter().enumerate() {
+                    match condition {
+                        Condition::Elimination => {
+                            if alive.len() <= 1 {
+                                winners = Some(alive.clone());
+                            }
+                        }
+                        Condition::HoldPoint {
+                            x,
+                            y,
+                            w,
+                            h,
+                            seconds,
+                        } => {
+                            let area = Rect::with_size(*x, *y, *w, *h);
+                            let mut holders: Vec<usize> = unit_query
+                                .iter(world)
+                                .filter(|(cell, _, _)| area.point_in_rect(cell.point()))
+                                .filter_map(|(_, _, player)| team(player))
+                                .collect();
+                            holders.sort_unstable();
+                            holders.dedup();
+                            let holder = if holders.len() == 1 {
+                                Some(holders[0])
+                            } else {
+                                None
+                            };
+                            if outcome.hold(i, holder, time.dt()) >= *seconds {
+                                winners = holder.map(|team| vec![team]);
+                            }
+                        }
+                        Condition::Survive { team, seconds } => {
+                            // the team loses as soon as it has nothing left, to whoever remains
+                            if !alive.contains(team) {
+                                winners =
+                                    Some(alive.iter().copied().filter(|t| t != team).collect());
+                            } else if elapsed >= *seconds {
+                                winners = Some(vec![*team]);
+                            }
+                        }
+                    }
+                    if winners.is_some() {
+                        break;
+                    }
+                }
+
+                if let Some(winners) = winners {
+                    outcome.finish(winners, time.tick());
+                }
+            },
+        )
+}
+
+/// Work out the tiles each team can see from the sight of its units and buildings, with rocks and
+/// cliffs blocking the view past them
+pub fn update_vision() -> impl ParallelRunnable {
+    SystemBuilder::new("update_vision")
+        .read_resource::<Map>()
+        .read_resource::<Players>()
+        .write_resource::<Vision>()
+        .with_query(<(Read<GameCell>, Read<Unit>, Read<Player>)>::query())
+        .with_query(<(Read<GameCell>, Read<Building>, Read<Player>)>::query())
+        .build(
+            |_, world, (map, players, vision), (unit_query, building_query)| {
+                let mut eyes = Vec::new();
+                for (cell, unit, player) in unit_query.iter(world) {
+                    if let Some(info) = players.get(*player) {
+                        eyes.push((info.team(), cell.point(), unit.sight()));
+                    }
+                }
+                for (cell, building, player) in building_query.iter(world) {
+                    if let Some(info) = players.get(*player) {
+                        eyes.push((
+                            info.team(),
+                            cell.footprint().center(),
+                            building.kind().sight(),
+                        ));
+                    }
+                }
+
+                vision.clear();
+                for (team, point, sight) in eyes {
+                    for seen in field_of_view(point, sight as i32, &**map) {
+                        vision.reveal(team, seen);
+                    }
+                }
+            },
+        )
+}
@@ -0,0 +1,228 @@
+use super::*;
+use crate::types::{Condition, Mode, Race};
+
+/// Return where every player's units are and what each player has done, to compare two runs of
+/// a match whatever order their entities are kept in
+fn snapshot(sim: &Simulation) -> Vec<(Vec<(i32, i32)>, PlayerStats)> {
+    let players = sim.resources().get::<Players>().unwrap().all();
+    players
+        .into_iter()
+        .map(|player| {
+            let mut positions: Vec<(i32, i32)> = sim
+                .units(player)
+                .into_iter()
+                .filter_map(|e| sim.position(e))
+                .map(|point| (point.x, point.y))
+                .collect();
+            positions.sort_unstable();
+            (positions, sim.stats(player))
+        })
+        .collect()
+}
+
+#[test]
+fn same_seed_plays_out_the_same() {
+    let mut a = Simulation::skirmish(42);
+    let mut b = Simulation::skirmish(42);
+    a.add_computers(Difficulty::Insane);
+    b.add_computers(Difficulty::Insane);
+
+    for _ in 0..6 {
+        a.run(300);
+        b.run(300);
+        assert_eq!(snapshot(&a), snapshot(&b), "diverged by tick {}", a.tick());
+    }
+}
+
+/// Return an empty match on open ground between two players on opposing teams
+fn duel() -> Simulation {
+    let players = Players::new(0)
+        .with_player(PlayerInfo::new("Blue", Race::Alien, 0, RGB::named(BLUE)))
+        .with_player(PlayerInfo::new("Red", Race::Bug, 1, RGB::named(RED)));
+    Simulation::new(Map::new(40, 20), players, 0)
+}
+
+#[test]
+fn units_move_where_they_are_sent() {
+    let mut sim = duel();
+    let blue = Player::new(0);
+    let trooper = sim.spawn_unit(UnitKind::Trooper, Point::new(-10, 0), blue);
+
+    sim.command(blue, Command::Select(vec![trooper]));
+    sim.command(blue, Command::Move(Point::new(5, 3), Mode::Move));
+    sim.run(150);
+
+    assert_eq!(sim.position(trooper), Some(Point::new(5, 3)));
+}
+
+#[test]
+fn units_attack_and_eliminate_a_player() {
+    let mut sim = duel();
+    let (blue, red) = (Player::new(0), Player::new(1));
+    let troopers = vec![
+        sim.spawn_unit(UnitKind::Trooper, Point::new(-10, 0), blue),
+        sim.spawn_unit(UnitKind::Trooper, Point::new(-10, 1), blue),
+    ];
+    let spider = sim.spawn_unit(UnitKind::FleshSpider, Point::new(8, 0), red);
+
+    sim.command(blue, Command::Select(troopers.clone()));
+    sim.command(blue, Command::Attack(spider));
+    sim.run(900);
+
+    assert!(sim.units(red).is_empty());
+    assert!(sim.is_eliminated(red));
+    assert_eq!(sim.units(blue).len(), troopers.len());
+    assert!(!sim.is_eliminated(blue));
+}
+
+/// Send every unit of the first player in a skirmish to the point
+fn send_all(sim: &mut Simulation, destination: Point) {
+    let blue = Player::new(0);
+    let units = sim.units(blue);
+    sim.command(blue, Command::Select(units));
+    sim.command(blue, Command::Move(destination, Mode::Attack));
+}
+
+#[test]
+fn loaded_match_plays_out_as_if_never_saved() {
+    let path = std::env::temp_dir().join("text_rts_continue_test_save.ron");
+    let mut sim = Simulation::skirmish(7);
+    sim.add_computers(Difficulty::Normal);
+    send_all(&mut sim, Point::new(0, 0));
+    sim.run(300);
+    sim.save(&path, &()).unwrap();
+
+    let (mut loaded, ()) = Simulation::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    send_all(&mut sim, Point::new(10, 5));
+    send_all(&mut loaded, Point::new(10, 5));
+    sim.run(300);
+    loaded.run(300);
+
+    assert_eq!(snapshot(&loaded), snapshot(&sim));
+}
+
+#[test]
+fn replay_of_a_loaded_match_covers_the_whole_match() {
+    let path = std::env::temp_dir().join("text_rts_replay_test_save.ron");
+    let mut sim = Simulation::skirmish(7);
+    sim.add_computers(Difficulty::Normal);
+    send_all(&mut sim, Point::new(0, 0));
+    sim.run(300);
+    sim.save(&path, &()).unwrap();
+
+    let (mut loaded, ()) = Simulation::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    send_all(&mut loaded, Point::new(10, 5));
+    loaded.run(300);
+
+    let replay = loaded.replay();
+    assert_eq!(replay.length(), 600);
+    let mut playback = Playback::new(replay);
+    let mut played = playback.restart();
+    playback.seek(&mut played, loaded.tick());
+    assert_eq!(played.tick(), 600);
+    assert_eq!(snapshot(&played), snapshot(&loaded));
+}
+
+#[test]
+fn skirmish_players_start_within_their_supply() {
+    let mut sim = Simulation::skirmish(1);
+    sim.run(1);
+    let players = sim.resources().get::<Players>().unwrap();
+    for player in players.all() {
+        let (used, cap) = players.get(player).unwrap().supply();
+        assert!(
+            used <= cap,
+            "player {} starts at {}/{}",
+            player.id(),
+            used,
+            cap
+        );
+    }
+}
+
+#[test]
+fn scenarios_shipped_with_the_game_are_valid() {
+    let (scenarios, errors) = Scenario::load_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/scenarios"));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert!(scenarios.iter().any(|scenario| scenario.name == "Skirmish"));
+}
+
+#[test]
+fn scenarios_placing_a_building_on_a_unit_are_rejected() {
+    let mut scenario = Scenario::skirmish();
+    let hq = &scenario.buildings[0];
+    let (x, y) = (hq.x + 1, hq.y + 1);
+    scenario.units[0].x = x;
+    scenario.units[0].y = y;
+    let s = ron::ser::to_string(&scenario).unwrap();
+    assert!(Scenario::parse(&s).is_err());
+}
+
+#[test]
+fn holding_a_point_alone_wins() {
+    let mut sim = duel();
+    sim.resources_mut()
+        .insert(Outcome::new(vec![Condition::HoldPoint {
+            x: -1,
+            y: -1,
+            w: 3,
+            h: 3,
+            seconds: 2.0,
+        }]));
+    sim.spawn_unit(UnitKind::Trooper, Point::new(0, 0), Player::new(0));
+    sim.spawn_unit(UnitKind::FleshSpider, Point::new(18, 8), Player::new(1));
+
+    sim.run(30);
+    assert!(!sim.outcome().is_finished());
+    sim.run(60);
+    assert_eq!(sim.outcome().winners(), Some(&[0][..]));
+}
+
+#[test]
+fn surviving_until_the_time_runs_out_wins() {
+    let mut sim = duel();
+    sim.resources_mut()
+        .insert(Outcome::new(vec![Condition::Survive {
+            team: 1,
+            seconds: 2.0,
+        }]));
+    sim.spawn_unit(UnitKind::Trooper, Point::new(-18, -8), Player::new(0));
+    sim.spawn_unit(UnitKind::FleshSpider, Point::new(18, 8), Player::new(1));
+
+    sim.run(30);
+    assert!(!sim.outcome().is_finished());
+    sim.run(60);
+    assert_eq!(sim.outcome().winners(), Some(&[1][..]));
+}
+
+#[test]
+fn scenarios_with_impossible_conditions_are_rejected() {
+    let conditions = vec![
+        Condition::HoldPoint {
+            x: 48,
+            y: 0,
+            w: 5,
+            h: 5,
+            seconds: 10.0,
+        },
+        Condition::HoldPoint {
+            x: 0,
+            y: 0,
+            w: 5,
+            h: 5,
+            seconds: 0.0,
+        },
+        Condition::Survive {
+            team: 0,
+            seconds: -1.0,
+        },
+    ];
+    for condition in conditions {
+        let mut scenario = Scenario::skirmish();
+        scenario.victory = vec![condition.clone()];
+        let s = ron::ser::to_string(&scenario).unwrap();
+        assert!(Scenario::parse(&s).is_err(), "{:?}", condition);
+    }
+}
@@ -5,259 +5,554 @@ use bracket_lib::prelude::*;
 
 use legion::*;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    components::{GameCell, Unit},
-    types::{CtrlGroups, Direction, Map, Mode, Mouse, Race, UnitKind},
+    components::{Building, GameCell, Player, Rally, ResourceNode, Unit, QUEUE_SIZE},
+    simulation::{save::entity_list, Playback, Replay, Scenario, Simulation, TICK_SECONDS},
+    types::{
+        BuildingKind, Button, Command, CtrlGroups, Difficulty, Direction, Effect, Map, Mode, Mouse,
+        PlayerInfo, Players, UnitKind, Vision,
+    },
 };
 
 const WHITE: (u8, u8, u8) = (255, 255, 255);
 const DARK_GRAY: (u8, u8, u8) = (100, 100, 100);
-const BROWN: (u8, u8, u8) = (170, 30, 0);
 const GREEN: (u8, u8, u8) = (0, 170, 0);
-const DARK_GREEN: (u8, u8, u8) = (0, 120, 0);
+
+/// The most ticks run in a single frame, so a long stall doesn't freeze the game catching up
+const MAX_STEPS: u32 = 5;
+
+/// The number of seconds damage numbers and death animations stay on screen
+const EFFECT_SECONDS: f32 = 1.0;
+
+/// Where the replay of the last match played is kept
+const REPLAY_PATH: &str = "last_replay.ron";
+/// Where the scenarios listed on the menu are read from
+const SCENARIO_DIR: &str = "scenarios";
+/// Where the match is saved from the pause menu or with the quick-save hotkey
+const SAVE_PATH: &str = "save.ron";
+/// The width and height of the minimap in the bottom left corner, not counting its border
+const MINIMAP_SIZE: (i32, i32) = (24, 12);
+/// The height of the bottom bar, which has room for the minimap inside its border
+const BAR_HEIGHT: i32 = MINIMAP_SIZE.1 + 2;
+/// The height of the bar shown in place of the bottom bar when watching a replay
+const REPLAY_BAR_HEIGHT: i32 = 5;
+/// The speeds a replay can be watched at
+const REPLAY_SPEEDS: [f32; 5] = [0.5, 1.0, 2.0, 4.0, 8.0];
+/// The number of seconds skipped when seeking through a replay
+const SEEK_SECONDS: f32 = 10.0;
+
+/// Return a number of ticks as minutes and seconds of game time
+fn clock(ticks: u64) -> String {
+    let seconds = (ticks as f32 * TICK_SECONDS) as u64;
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Return green for healthy units through yellow to red for those about to die, given the
+/// fraction of their HP they have left
+fn hp_color(hp: f32) -> RGB {
+    if hp > 0.66 {
+        RGB::named(GREEN)
+    } else if hp > 0.33 {
+        RGB::named(YELLOW)
+    } else {
+        RGB::named(RED)
+    }
+}
+
+/// Return the minerals and gas of a cost as shown in the bottom bar
+fn cost_label(cost: (u32, u32)) -> String {
+    if cost.1 > 0 {
+        format!("%{} &{}", cost.0, cost.1)
+    } else {
+        format!("%{}", cost.0)
+    }
+}
 
 #[derive(Clone, Debug)]
 pub enum CurrentState {
     Menu,
     Playing,
+    Paused,
+    Finished,
+    Replay,
     Quitting,
 }
 
+/// What the interface keeps about a match outside the Simulation, written to saves alongside it
+#[derive(Serialize, Deserialize)]
+struct Session {
+    offset: (i32, i32),
+    #[serde(with = "entity_list")]
+    selected: Vec<Entity>,
+    ctrl_groups: CtrlGroups,
+    difficulty: Difficulty,
+}
+
 pub struct State {
     curr_state: CurrentState,
-    world: World,
-    schedule: Schedule,
+    sim: Simulation,
     window_size: (u32, u32),
     tic: u8,
     dt: f32,
+    accumulator: f32,
     #[cfg(not(target_arch = "wasm32"))]
     instant: Instant,
     offset: (i32, i32),
     mouse: Mouse,
     mode: Mode,
+    placing: Option<BuildingKind>,
     selected: Vec<Entity>,
     ctrl_groups: CtrlGroups,
-    map: Map,
-    supply: (i32, i32),
+    warning: Option<(&'static str, f32)>,
+    difficulty: Difficulty,
+    playback: Option<Playback>,
+    paused: bool,
+    speed: usize,
+    scenarios: Vec<Scenario>,
+    scenario: usize,
+    overlay: bool,
+    effects: Vec<(Effect, f32)>,
 }
 
 impl State {
     pub fn new(w: u32, h: u32) -> Self {
-        let mut world = World::default();
-
-        let mut units = Vec::with_capacity(145);
-        for x in 0..20 {
-            units.push((
-                GameCell::new(10 - (x & 1), x + 5, 'V', RGB::named(GREEN)),
-                Unit::new(Race::Bionic, UnitKind::Blademaster, 30)
-                    .with_damage(5)
-                    .with_speed(14.5),
-            ));
-            units.push((
-                GameCell::new(7 - (x & 1), x + 5, 'Y', RGB::named(DARK_GREEN)),
-                Unit::new(Race::Bionic, UnitKind::Strider, 40)
-                    .with_damage(5)
-                    .with_range(10, 13),
-            ));
-        }
-        for _ in 0..5 {
-            for y in 0..20 {
-                units.push((
-                    GameCell::new(45, 5 + y, '*', RGB::named(BROWN)),
-                    Unit::new(Race::Bug, UnitKind::FleshSpider, 15),
-                ));
-            }
-        }
-        for x in 0..5 {
-            units.push((
-                GameCell::new(x + 25, 35, 'T', RGB::named(BLUE)),
-                Unit::new(Race::Alien, UnitKind::Trooper, 20)
-                    .with_damage(2)
-                    .with_range(5, 8)
-                    .with_speed(10.5),
-            ));
-        }
-        world.extend(units);
-
-        let bump_units = SystemBuilder::new("bump_units")
-            .with_query(<(Read<GameCell>,)>::query().filter(component::<Unit>()))
-            .with_query(<(Read<GameCell>,)>::query())
-            .write_component::<GameCell>()
-            .build(|_, world, _, (query, inner_query)| {
-                let mut bumped = Vec::new();
-                for chunk in query.iter_chunks(world) {
-                    for (e, (cell,)) in chunk.into_iter_entities() {
-                        if !cell.is_holding() {
-                            for inner_chunk in inner_query.iter_chunks(world) {
-                                for (e2, (cell2,)) in inner_chunk.into_iter_entities() {
-                                    if e != e2 && cell.point() == cell2.point() {
-                                        bumped.push(e);
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                for e in bumped.iter() {
-                    if let Ok(cell) = world.entry_mut(*e).unwrap().get_component_mut::<GameCell>() {
-                        cell.bump();
-                    }
-                }
-            });
-
-        let attack_units = SystemBuilder::new("attack_units")
-            .with_query(<(Read<GameCell>, Read<Unit>)>::query())
-            .with_query(<(Read<GameCell>, Read<Unit>)>::query())
-            .with_query(<(Read<GameCell>, Read<Unit>)>::query())
-            .write_component::<GameCell>()
-            .write_component::<Unit>()
-            .build(|_, world, _, (query, attack_query, moving_query)| {
-                let mut attacking_units = Vec::new();
-                let mut moving_units = Vec::new();
-                for chunk in query.iter_chunks(world) {
-                    for (e, (cell, unit)) in chunk.into_iter_entities() {
-                        let mut attacked = false;
-                        for attack_chunk in attack_query.iter_chunks(world) {
-                            for (e2, (cell2, unit2)) in attack_chunk.into_iter_entities() {
-                                if unit.race() != unit2.race()
-                                    && cell.range_rect(unit.range()).point_in_rect(cell2.point())
-                                {
-                                    attacking_units.push((e, e2, unit.attack(), cell.point()));
-                                    attacked = true;
-                                    break;
-                                }
-                            }
-                        }
-                        if !attacked && !cell.is_holding() {
-                            for (cell2, unit2) in moving_query.iter(world) {
-                                if unit.race() != unit2.race()
-                                    && cell
-                                        .range_rect(unit.follow_dist())
-                                        .point_in_rect(cell2.point())
-                                {
-                                    moving_units.push((e, cell2.point()));
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
-                for (e, e2, attack, pt) in attacking_units.iter() {
-                    let is_moving = if let Ok(cell) =
-                        world.entry_mut(*e).unwrap().get_component_mut::<GameCell>()
-                    {
-                        if !cell.is_moving() {
-                            cell.stop_moving();
-                            false
-                        } else {
-                            true
-                        }
-                    } else {
-                        true
-                    };
-
-                    if !is_moving {
-                        if let Some(damage) = *attack {
-                            if let Ok(unit) =
-                                world.entry_mut(*e).unwrap().get_component_mut::<Unit>()
-                            {
-                                unit.reset_tic();
-                            }
-
-                            if let Ok(cell2) = world
-                                .entry_mut(*e2)
-                                .unwrap()
-                                .get_component_mut::<GameCell>()
-                            {
-                                cell2.set_harmed();
-                                cell2.move_to_attacker(*pt);
-                            }
-                            if let Ok(unit2) =
-                                world.entry_mut(*e2).unwrap().get_component_mut::<Unit>()
-                            {
-                                unit2.harm(damage);
-                            }
-                        }
-                    }
-                }
-                for (e, pt2) in moving_units.iter() {
-                    if let Ok(cell) = world.entry_mut(*e).unwrap().get_component_mut::<GameCell>() {
-                        cell.move_towards(*pt2);
-                    }
-                }
-            });
-
-        let clear_units = SystemBuilder::new("clear_units")
-            .with_query(<(Read<Unit>,)>::query().filter(maybe_changed::<Unit>()))
-            .write_component::<Unit>()
-            .build(|commands, world, _, query| {
-                let mut deleted = Vec::new();
-                for chunk in query.iter_chunks(world) {
-                    for (e, (unit,)) in chunk.into_iter_entities() {
-                        if unit.hp() <= 0 {
-                            deleted.push(e);
-                        }
-                    }
-                }
-                for e in deleted.iter() {
-                    commands.remove(*e);
-                }
-            });
-
-        let schedule = Schedule::builder()
-            .add_system(bump_units)
-            .add_system(attack_units)
-            .add_system(clear_units)
-            .flush()
-            .build();
+        let (mut scenarios, errors) = Scenario::load_dir(SCENARIO_DIR);
+        for (path, e) in errors.iter() {
+            eprintln!("{}: {}", path, e);
+        }
+        if scenarios.is_empty() {
+            scenarios.push(Scenario::skirmish());
+        }
+        let scenario = scenarios
+            .iter()
+            .position(|scenario| scenario.name == Scenario::skirmish().name)
+            .unwrap_or(0);
 
-        Self {
+        let mut state = Self {
             curr_state: CurrentState::Menu,
-            world,
-            schedule,
+            sim: Simulation::skirmish(rand::random()),
             window_size: (w, h),
             dt: 0.016,
+            accumulator: 0.0,
             #[cfg(not(target_arch = "wasm32"))]
             instant: Instant::now(),
             tic: 0,
             offset: (0, 0),
             mouse: Mouse::new(),
             mode: Mode::Select,
+            placing: None,
             selected: Vec::new(),
             ctrl_groups: CtrlGroups::new(),
-            map: Map::new(100, 100),
-            supply: (0, 99),
+            warning: None,
+            difficulty: Difficulty::Normal,
+            playback: None,
+            paused: false,
+            speed: 1,
+            scenarios,
+            scenario,
+            overlay: true,
+            effects: Vec::new(),
+        };
+        if !errors.is_empty() {
+            state.warn("Some scenarios could not be loaded, see the console for why");
         }
+        state
     }
 
     fn menu_state(&mut self, ctx: &mut BTerm) {
-        ctx.print_centered(self.window_size.1 as i32 / 2 - 1, "TextRTS");
+        let scenario = &self.scenarios[self.scenario];
+        ctx.print_centered(self.window_size.1 as i32 / 2 - 5, "TextRTS");
+        ctx.print_centered(
+            self.window_size.1 as i32 / 2 - 3,
+            format!("^ Scenario: {} v", scenario.name),
+        );
+        ctx.print_color_centered(
+            self.window_size.1 as i32 / 2 - 2,
+            RGB::named(DARK_GRAY),
+            RGB::named(BLACK),
+            &scenario.description,
+        );
         ctx.print_centered(
             self.window_size.1 as i32 / 2 + 1,
+            format!("< Difficulty: {} >", self.difficulty.name()),
+        );
+        ctx.print_centered(
+            self.window_size.1 as i32 / 2 + 3,
             "Press the spacebar to start",
         );
+        ctx.print_centered(
+            self.window_size.1 as i32 / 2 + 5,
+            "Press R to watch the last match",
+        );
+        ctx.print_centered(
+            self.window_size.1 as i32 / 2 + 7,
+            "Press L to load the saved match",
+        );
 
-        if let Some(VirtualKeyCode::Space) = ctx.key {
-            self.curr_state = CurrentState::Playing;
+        self.print_warning(ctx);
+
+        match ctx.key {
+            Some(VirtualKeyCode::Left) => self.difficulty = self.difficulty.easier(),
+            Some(VirtualKeyCode::Right) => self.difficulty = self.difficulty.harder(),
+            Some(VirtualKeyCode::Up) => {
+                self.scenario = (self.scenario + self.scenarios.len() - 1) % self.scenarios.len()
+            }
+            Some(VirtualKeyCode::Down) => {
+                self.scenario = (self.scenario + 1) % self.scenarios.len()
+            }
+            Some(VirtualKeyCode::Space) => self.start(),
+            Some(VirtualKeyCode::R) => self.watch_replay(),
+            Some(VirtualKeyCode::L) => self.load_game(),
+            _ => (),
         }
     }
 
+    /// Set up the chosen scenario, hand every player but the local one to a computer opponent of
+    /// the chosen difficulty and begin the match
+    fn start(&mut self) {
+        self.sim = Simulation::from_scenario(&self.scenarios[self.scenario], rand::random());
+        self.sim.add_computers(self.difficulty);
+        self.curr_state = CurrentState::Playing;
+    }
+
+    /// Write the match and everything the interface keeps about it to the save file
+    fn save_game(&mut self) {
+        let session = Session {
+            offset: self.offset,
+            selected: self.selected.clone(),
+            ctrl_groups: self.ctrl_groups.clone(),
+            difficulty: self.difficulty,
+        };
+        match self.sim.save(SAVE_PATH, &session) {
+            Ok(()) => self.warn("Game saved"),
+            Err(_) => self.warn("The game could not be saved"),
+        }
+    }
+
+    /// Replace the current match with the one in the save file and resume playing it
+    fn load_game(&mut self) {
+        match Simulation::load::<_, Session>(SAVE_PATH) {
+            Ok((sim, session)) => {
+                self.sim = sim;
+                self.offset = session.offset;
+                self.selected = session.selected;
+                self.ctrl_groups = session.ctrl_groups;
+                self.difficulty = session.difficulty;
+                self.mode = Mode::Select;
+                self.placing = None;
+                self.accumulator = 0.0;
+                self.curr_state = CurrentState::Playing;
+            }
+            Err(_) => self.warn("There is no saved game to load"),
+        }
+    }
+
+    /// Show the match frozen behind a menu to resume, save, load or leave it
+    fn pause_state(&mut self, ctx: &mut BTerm) {
+        self.print_grid(ctx);
+        self.render_nodes(ctx);
+        self.render_buildings(ctx);
+        self.render_cells(ctx);
+
+        let (x, y) = (
+            self.window_size.0 as i32 / 2 - 12,
+            self.window_size.1 as i32 / 2 - 5,
+        );
+        ctx.draw_box(x, y, 24, 9, RGB::named(WHITE), RGB::named(BLACK));
+        ctx.print_centered(y + 2, "Paused");
+        ctx.print_centered(y + 4, "R : Resume");
+        ctx.print_centered(y + 5, "S : Save");
+        ctx.print_centered(y + 6, "L : Load");
+        ctx.print_centered(y + 7, "Q : Quit to menu");
+
+        self.print_warning(ctx);
+
+        match ctx.key {
+            Some(VirtualKeyCode::R) | Some(VirtualKeyCode::Escape) | Some(VirtualKeyCode::F10) => {
+                self.curr_state = CurrentState::Playing
+            }
+            Some(VirtualKeyCode::S) => self.save_game(),
+            Some(VirtualKeyCode::L) => self.load_game(),
+            Some(VirtualKeyCode::Q) => {
+                // leaving the match is not worth interrupting over a replay that can't be written
+                let _ = self.sim.replay().save(REPLAY_PATH);
+                self.sim = Simulation::skirmish(rand::random());
+                self.selected.clear();
+                self.ctrl_groups = CtrlGroups::new();
+                self.mode = Mode::Select;
+                self.placing = None;
+                self.curr_state = CurrentState::Menu;
+            }
+            _ => (),
+        }
+    }
+
+    /// Leave the match for the post-match screen, keeping its replay to watch later
+    fn finish(&mut self) {
+        // the match is over either way, so a replay that can't be written is simply lost
+        let _ = self.sim.replay().save(REPLAY_PATH);
+        self.mode = Mode::Select;
+        self.placing = None;
+        self.curr_state = CurrentState::Finished;
+    }
+
+    /// Show how the match ended and what each player did in it
+    fn finished_state(&mut self, ctx: &mut BTerm) {
+        let outcome = self.sim.outcome();
+        let local = self.local_player();
+        let local_team = self.local_info().team();
+        let players: Vec<(Player, PlayerInfo)> = self
+            .sim
+            .resources()
+            .get::<Players>()
+            .unwrap()
+            .all()
+            .into_iter()
+            .filter_map(|player| self.sim.player(player).map(|info| (player, info)))
+            .collect();
+
+        let (title, winners) = match outcome.winners() {
+            Some(winners) => {
+                let names: Vec<&str> = players
+                    .iter()
+                    .filter(|(_, info)| winners.contains(&info.team()))
+                    .map(|(_, info)| info.name())
+                    .collect();
+                let title = if winners.is_empty() {
+                    "Draw"
+                } else if winners.contains(&local_team) {
+                    "Victory"
+                } else {
+                    "Defeat"
+                };
+                (
+                    title,
+                    if names.is_empty() {
+                        "None".to_string()
+                    } else {
+                        names.join(", ")
+                    },
+                )
+            }
+            // the local player was wiped out while the others fight on
+            None => ("Defeat", "Undecided".to_string()),
+        };
+        let duration = outcome.end().unwrap_or_else(|| self.sim.tick());
+
+        let y = self.window_size.1 as i32 / 2 - 6 - players.len() as i32 / 2;
+        ctx.print_centered(y, title);
+        ctx.print_centered(y + 2, format!("Winner: {}", winners));
+        ctx.print_centered(y + 3, format!("Duration: {}", clock(duration)));
+
+        let x = self.window_size.0 as i32 / 2 - 22;
+        ctx.print_color(
+            x,
+            y + 5,
+            RGB::named(DARK_GRAY),
+            RGB::named(BLACK),
+            format!(
+                "{:<12}{:>10}{:>10}{:>12}",
+                "Player", "Produced", "Lost", "Damage"
+            ),
+        );
+        for (i, (player, info)) in players.iter().enumerate() {
+            let stats = self.sim.stats(*player);
+            ctx.print_color(
+                x,
+                y + 6 + i as i32,
+                info.color(),
+                RGB::named(BLACK),
+                format!(
+                    "{:<12}{:>10}{:>10}{:>12}{}",
+                    info.name(),
+                    stats.produced,
+                    stats.lost,
+                    stats.damage_dealt,
+                    if *player == local { " (you)" } else { "" }
+                ),
+            );
+        }
+
+        ctx.print_centered(
+            y + 8 + players.len() as i32,
+            "Press the spacebar to return to the menu or R to watch the replay",
+        );
+
+        match ctx.key {
+            Some(VirtualKeyCode::Space) | Some(VirtualKeyCode::Escape) => {
+                self.sim = Simulation::skirmish(rand::random());
+                self.selected.clear();
+                self.ctrl_groups = CtrlGroups::new();
+                self.curr_state = CurrentState::Menu;
+            }
+            Some(VirtualKeyCode::R) => self.watch_replay(),
+            _ => (),
+        }
+    }
+
+    /// Load the replay of the last match and start watching it from the beginning
+    fn watch_replay(&mut self) {
+        match Replay::load(REPLAY_PATH) {
+            Ok(replay) => {
+                let mut playback = Playback::new(replay);
+                self.sim = playback.restart();
+                self.playback = Some(playback);
+                self.paused = false;
+                self.speed = 1;
+                self.accumulator = 0.0;
+                self.curr_state = CurrentState::Replay;
+            }
+            Err(_) => self.warn("There is no replay to watch"),
+        }
+    }
+
+    fn replay_state(&mut self, ctx: &mut BTerm) {
+        let mut playback = match self.playback.take() {
+            Some(playback) => playback,
+            None => {
+                self.curr_state = CurrentState::Menu;
+                return;
+            }
+        };
+
+        if !self.paused {
+            let speed = REPLAY_SPEEDS[self.speed];
+            self.accumulator += self.dt * speed;
+            let mut steps = 0;
+            while self.accumulator >= TICK_SECONDS
+                && steps < MAX_STEPS * speed.ceil() as u32
+                && !playback.is_finished(&self.sim)
+            {
+                playback.step(&mut self.sim);
+                self.take_effects();
+                self.accumulator -= TICK_SECONDS;
+                steps += 1;
+            }
+            self.accumulator = self.accumulator.min(TICK_SECONDS);
+            if playback.is_finished(&self.sim) {
+                self.paused = true;
+            }
+        }
+
+        self.print_grid(ctx);
+        self.render_nodes(ctx);
+        self.render_buildings(ctx);
+        self.render_cells(ctx);
+        self.print_overlay(ctx);
+        self.print_replay_bar(ctx, &playback);
+
+        let seek = (SEEK_SECONDS / TICK_SECONDS) as u64;
+        if let Some(key) = ctx.key {
+            match key {
+                VirtualKeyCode::Space => self.paused = !self.paused,
+                VirtualKeyCode::Minus => self.speed = self.speed.saturating_sub(1),
+                VirtualKeyCode::Equals => {
+                    self.speed = (self.speed + 1).min(REPLAY_SPEEDS.len() - 1)
+                }
+                VirtualKeyCode::LBracket => {
+                    playback.seek(&mut self.sim, self.sim.tick().saturating_sub(seek))
+                }
+                VirtualKeyCode::RBracket => playback.seek(&mut self.sim, self.sim.tick() + seek),
+                VirtualKeyCode::V => self.overlay = !self.overlay,
+                VirtualKeyCode::Up => self.scroll(Direction::N),
+                VirtualKeyCode::Down => self.scroll(Direction::S),
+                VirtualKeyCode::Left => self.scroll(Direction::W),
+                VirtualKeyCode::Right => self.scroll(Direction::E),
+                VirtualKeyCode::Escape => {
+                    self.sim = Simulation::skirmish(rand::random());
+                    self.curr_state = CurrentState::Menu;
+                    return;
+                }
+                _ => (),
+            }
+        }
+
+        self.playback = Some(playback);
+    }
+
+    /// Draw the replay's progress, speed and controls in place of the bottom bar
+    fn print_replay_bar(&self, ctx: &mut BTerm, playback: &Playback) {
+        let y = self.window_size.1 as i32 - REPLAY_BAR_HEIGHT;
+        ctx.draw_box(
+            0,
+            y,
+            self.window_size.0 - 1,
+            REPLAY_BAR_HEIGHT - 1,
+            RGB::named(WHITE),
+            RGB::named(BLACK),
+        );
+        ctx.print_color(
+            2,
+            y + 1,
+            RGB::named(WHITE),
+            RGB::named(BLACK),
+            format!(
+                "Replay  {} / {}  x{}{}",
+                clock(self.sim.tick()),
+                clock(playback.length()),
+                REPLAY_SPEEDS[self.speed],
+                if self.paused { "  Paused" } else { "" }
+            ),
+        );
+        ctx.draw_bar_horizontal(
+            2,
+            y + 2,
+            self.window_size.0 as i32 - 5,
+            self.sim.tick() as i32,
+            playback.length().max(1) as i32,
+            RGB::named(GREEN),
+            RGB::named(DARK_GRAY),
+        );
+        ctx.print_color(
+            2,
+            y + 3,
+            RGB::named(WHITE),
+            RGB::named(BLACK),
+            "Space Play/Pause  - = Speed  [ ] Seek  Esc Menu",
+        );
+    }
+
     fn play_state(&mut self, ctx: &mut BTerm) {
-        let mut resources = Resources::default();
-        self.schedule.execute(&mut self.world, &mut resources);
+        // the simulation runs in fixed ticks however long the frame took, carrying the remainder
+        // over so rendering can interpolate between the last two ticks
+        self.accumulator += self.dt;
+        let mut steps = 0;
+        while self.accumulator >= TICK_SECONDS && steps < MAX_STEPS {
+            self.sim.step();
+            self.take_effects();
+            self.accumulator -= TICK_SECONDS;
+            steps += 1;
+        }
+        self.accumulator = self.accumulator.min(TICK_SECONDS);
+        if self.sim.outcome().is_finished() || self.sim.is_eliminated(self.local_player()) {
+            self.finish();
+        }
 
         self.print_grid(ctx);
 
-        if self.mouse.y() < self.window_size.1 as i32 - 5 {
+        if self.mouse.y() < self.view_height() {
             self.mouse.print_cursor(ctx, self.mode, self.tic);
         }
 
+        self.render_nodes(ctx);
+
+        self.render_buildings(ctx);
+
         self.render_cells(ctx);
 
+        self.print_overlay(ctx);
+
+        self.print_rallies(ctx);
+
+        self.print_placement(ctx);
+
         self.print_mode(ctx);
 
-        self.print_cash(ctx);
+        self.print_resources(ctx);
+
+        self.print_warning(ctx);
 
         self.mouse_input();
 
@@ -276,24 +571,26 @@ impl State {
     }
 
     fn scroll(&mut self, d: Direction) {
+        let map = self.sim.resources().get::<Map>().unwrap();
+
         match d {
             Direction::N => {
-                if self.offset.1 < self.map.upper_y() {
+                if self.offset.1 < map.upper_y() {
                     self.offset.1 += 1
                 }
             }
             Direction::S => {
-                if self.offset.1 > self.map.lower_y() {
+                if self.offset.1 > map.lower_y() {
                     self.offset.1 -= 1
                 }
             }
             Direction::E => {
-                if self.offset.0 > self.map.lower_x() {
+                if self.offset.0 > map.lower_x() {
                     self.offset.0 -= 1
                 }
             }
             Direction::W => {
-                if self.offset.0 < self.map.upper_x() {
+                if self.offset.0 < map.upper_x() {
                     self.offset.0 += 1
                 }
             }
@@ -301,6 +598,56 @@ impl State {
     }
 
     fn mouse_input(&mut self) {
+        // clicks on the minimap move the camera or send the selection there instead
+        if let Some(point) = self.minimap_point(self.mouse.point) {
+            match self.mouse.click {
+                Some((0, false)) => match self.mode() {
+                    Mode::Move | Mode::Attack => {
+                        self.command(Command::Move(point, self.mode()));
+                        self.set_mode(Mode::Select);
+                    }
+                    _ => self.center_camera(point),
+                },
+                Some((1, false)) => {
+                    self.command(Command::Move(point, Mode::Move));
+                    self.set_mode(Mode::Select);
+                }
+                _ => {
+                    if self.mouse.left_pressed() && self.mouse.is_pressed() {
+                        self.center_camera(point);
+                    }
+                }
+            }
+            return;
+        }
+        // clicks that began on the command card press the button they end on
+        let start = self.mouse.down;
+        if self.card_slot(start).is_some() {
+            if let (Some((0, false)), Some(slot)) =
+                (self.mouse.click, self.card_slot(self.mouse.point))
+            {
+                if let Some(button) = self.command_card()[slot] {
+                    match self.disabled_reason(button) {
+                        Some(reason) => self.warn(reason),
+                        None => self.press(button),
+                    }
+                }
+            }
+            return;
+        }
+        // clicks on a tile of the selection panel narrow the selection to that unit
+        if let Some(slot) = self.panel_slot(start) {
+            if let Some((0, false)) = self.mouse.click {
+                if self.panel_slot(self.mouse.point) == Some(slot) {
+                    if let Some((e, _, _)) = self.selected_units().get(slot) {
+                        self.select_only(*e);
+                    }
+                }
+            }
+            return;
+        }
+        let from_minimap = self.minimap_rect().point_in_rect(start);
+
         if self.mouse.select_one() {
             if self.mouse.x() <= 0 {
                 self.scroll(Direction::W);
@@ -316,16 +663,26 @@ impl State {
 
         match self.mouse.click {
             Some((0, false)) => match self.mode() {
+                // a drag that began on the minimap only ever moves the camera
+                Mode::Select | Mode::Add if from_minimap => (),
                 Mode::Select | Mode::Add => self.select_cells(),
                 Mode::Move | Mode::Attack => {
+                    self.rally_buildings(self.mode());
                     self.move_cells(self.mode());
                     self.set_mode(Mode::Select);
                 }
                 Mode::Ctrl => self.select_same(),
+                Mode::Build => self.place_building(),
                 _ => (),
             },
             Some((1, false)) => {
-                self.move_cells(Mode::Move);
+                if let Mode::Build = self.mode() {
+                    self.placing = None;
+                } else {
+                    self.rally_buildings(Mode::Move);
+                    self.move_cells(Mode::Move);
+                    self.gather_cells();
+                }
                 self.set_mode(Mode::Select);
             }
             _ => (),
@@ -372,21 +729,33 @@ impl State {
                     }
                     self.set_mode(Mode::Select);
                 }
-                _ => match key {
-                    VirtualKeyCode::M => {
-                        if !self.selected.is_empty() {
-                            self.set_mode(Mode::Move)
-                        }
-                    }
-                    VirtualKeyCode::A => {
-                        if !self.selected.is_empty() {
-                            self.set_mode(Mode::Attack)
+                Mode::Build => {
+                    let race = self.local_info().race();
+                    if let Some(kind) = race.buildings().iter().find(|kind| kind.hotkey() == key) {
+                        self.press(Button::Place(*kind));
+                    } else {
+                        match key {
+                            VirtualKeyCode::Escape => self.press(Button::Back),
+                            VirtualKeyCode::Up => self.scroll(Direction::N),
+                            VirtualKeyCode::Down => self.scroll(Direction::S),
+                            VirtualKeyCode::Left => self.scroll(Direction::W),
+                            VirtualKeyCode::Right => self.scroll(Direction::E),
+                            _ => (),
                         }
                     }
-                    VirtualKeyCode::B => self.set_mode(Mode::Build),
-                    VirtualKeyCode::S => self.stop_cells(),
-                    VirtualKeyCode::H => self.hold_cells(),
+                }
+                _ => match key {
+                    VirtualKeyCode::M => self.press(Button::Move),
+                    VirtualKeyCode::A => self.press(Button::Attack),
+                    VirtualKeyCode::B => self.press(Button::Build),
+                    VirtualKeyCode::Q => self.train_unit(0),
+                    VirtualKeyCode::W => self.train_unit(1),
+                    VirtualKeyCode::E => self.train_unit(2),
+                    VirtualKeyCode::C => self.press(Button::Cancel),
+                    VirtualKeyCode::S => self.press(Button::Stop),
+                    VirtualKeyCode::H => self.press(Button::Hold),
                     VirtualKeyCode::F => self.focus_cell(),
+                    VirtualKeyCode::V => self.overlay = !self.overlay,
 
                     VirtualKeyCode::LControl | VirtualKeyCode::RControl => {
                         self.set_mode(Mode::Ctrl)
@@ -402,6 +771,8 @@ impl State {
                     VirtualKeyCode::Down => self.scroll(Direction::S),
                     VirtualKeyCode::Left => self.scroll(Direction::W),
                     VirtualKeyCode::Right => self.scroll(Direction::E),
+                    VirtualKeyCode::F5 => self.save_game(),
+                    VirtualKeyCode::F10 => self.curr_state = CurrentState::Paused,
                     VirtualKeyCode::End => self.curr_state = CurrentState::Quitting,
                     _ => {
                         if let Some(n) = State::key_num(key) {
@@ -420,7 +791,12 @@ impl State {
         if let Mode::Select | Mode::Add = self.mode {
             self.mouse.selection.x2 = self.mouse.x();
             self.mouse.selection.y2 = self.mouse.y();
+            let from_minimap = self.minimap_rect().point_in_rect(self.mouse.down);
             if self.mouse.left_pressed() && self.mouse.is_pressed() {
+                // dragging across the minimap moves the camera rather than drawing a box
+                if from_minimap {
+                    return;
+                }
                 let x = if self.mouse.selection.x1 <= self.mouse.selection.x2 {
                     self.mouse.selection.x1
                 } else {
@@ -446,10 +822,44 @@ impl State {
         }
     }
 
+    /// Return the number of rows at the top of the screen that show the map, above the bar at the
+    /// bottom
+    fn view_height(&self) -> i32 {
+        match self.curr_state {
+            CurrentState::Replay => self.window_size.1 as i32 - REPLAY_BAR_HEIGHT,
+            _ => self.window_size.1 as i32 - BAR_HEIGHT,
+        }
+    }
+
+    /// Return the local player's team, whose vision the map is shown through, or None when the
+    /// whole map is shown as it is when watching a replay
+    fn fog_team(&self) -> Option<usize> {
+        match self.curr_state {
+            CurrentState::Replay => None,
+            _ => Some(self.local_info().team()),
+        }
+    }
+
     fn print_grid(&mut self, ctx: &mut BTerm) {
-        for x in 0..self.window_size.0 {
-            for y in 0..self.window_size.1 - 5 {
-                ctx.print_color(x as i32, y as i32, RGB::named(DARK_GRAY), RGB::new(), ".")
+        let team = self.fog_team();
+        let map = self.sim.resources().get::<Map>().unwrap();
+        let vision = self.sim.resources().get::<Vision>().unwrap();
+
+        for x in 0..self.window_size.0 as i32 {
+            for y in 0..self.view_height() {
+                let point = Point::new(x - self.offset.0, y - self.offset.1);
+                if let Some(tile) = map.tile(point) {
+                    // unexplored tiles stay blank and those out of sight are dimmed
+                    let color = match team {
+                        Some(team) if !vision.is_explored(team, point) => continue,
+                        Some(team) if !vision.is_visible(team, point) => {
+                            let color = tile.color();
+                            RGB::from_f32(color.r * 0.4, color.g * 0.4, color.b * 0.4)
+                        }
+                        _ => tile.color(),
+                    };
+                    ctx.print_color(x, y, color, RGB::new(), &tile.symbol().to_string())
+                }
             }
         }
     }
@@ -493,40 +903,297 @@ impl State {
         }
     }
 
-    fn print_cash(&self, ctx: &mut BTerm) {
+    fn print_resources(&self, ctx: &mut BTerm) {
+        let info = self.local_info();
+        let s = format!(
+            "%{}  &{}  {} / {}",
+            info.minerals(),
+            info.gas(),
+            info.supply().0,
+            info.supply().1
+        );
         ctx.print_color(
-            self.window_size.0 - 7,
+            self.window_size.0 as usize - s.len(),
             0,
             RGB::named(WHITE),
             RGB::named(BLACK),
-            format!(
-                "{s:>w$}",
-                s = format!("{} / {}", self.supply.0, self.supply.1),
-                w = 7
-            ),
+            s,
         );
     }
 
+    /// Draw a flag at the rally point of each selected building
+    fn print_rallies(&self, ctx: &mut BTerm) {
+        let mut query = <(Read<GameCell>, Read<Building>)>::query();
+        for (cell, building) in query.iter(self.sim.world()) {
+            if !cell.selected() {
+                continue;
+            }
+            let point = match building.rally() {
+                Some(Rally::Point(point, _)) => Some(point),
+                Some(Rally::Unit(target)) => {
+                    self.sim.world().entry_ref(target).ok().and_then(|entry| {
+                        entry
+                            .get_component::<GameCell>()
+                            .ok()
+                            .map(|cell| cell.point())
+                    })
+                }
+                None => None,
+            };
+            if let Some(point) = point {
+                ctx.print_color(
+                    point.x + self.offset.0,
+                    point.y + self.offset.1 - 1,
+                    RGB::named(YELLOW),
+                    RGB::new(),
+                    "!",
+                );
+            }
+        }
+    }
+
+    fn warn(&mut self, s: &'static str) {
+        self.warning = Some((s, 3.0));
+    }
+
+    fn print_warning(&mut self, ctx: &mut BTerm) {
+        if let Some((s, time)) = self.warning {
+            ctx.print_color_centered(2, RGB::named(RED), RGB::named(BLACK), s);
+            self.warning = if time > self.dt {
+                Some((s, time - self.dt))
+            } else {
+                None
+            };
+        }
+    }
+
+    fn print_placement(&mut self, ctx: &mut BTerm) {
+        if let (Mode::Build, Some(kind)) = (self.mode, self.placing) {
+            let origin = self.placement_origin(kind);
+            let color = if self.placement_valid(kind, origin) {
+                RGB::from_u8(0, 200, 0)
+            } else {
+                RGB::from_u8(200, 0, 0)
+            };
+            for (dy, row) in kind.art().iter().enumerate() {
+                let y = origin.y + dy as i32 + self.offset.1;
+                // the ghost never spills onto the bottom bar
+                if y >= self.view_height() {
+                    break;
+                }
+                for (dx, symbol) in row.chars().enumerate() {
+                    ctx.print_color(
+                        origin.x + dx as i32 + self.offset.0,
+                        y,
+                        color,
+                        RGB::from_u8(40, 40, 40),
+                        &symbol.to_string(),
+                    );
+                }
+            }
+        }
+    }
+
     fn print_bottom_bar(&self, ctx: &mut BTerm) {
         // full bar
         ctx.draw_box(
             0,
-            self.window_size.1 - 5,
+            self.view_height() as u32,
             self.window_size.0 - 1,
-            4,
+            BAR_HEIGHT as u32 - 1,
             RGB::named(WHITE),
             RGB::named(BLACK),
         );
-        // mini map
-        ctx.draw_box(
-            0,
-            self.window_size.1 - 5,
-            4,
-            4,
-            RGB::named(WHITE),
-            RGB::named(BLACK),
-        );
-        // command grid
+        self.print_minimap(ctx);
+
+        // the details of the selection start just right of the minimap
+        let x = MINIMAP_SIZE.0 + 3;
+        if let Mode::Build = self.mode {
+            let info = self.local_info();
+            for (index, kind) in info.race().buildings().iter().enumerate() {
+                let color = if info.can_afford(kind.cost()) {
+                    RGB::named(WHITE)
+                } else {
+                    RGB::named(DARK_GRAY)
+                };
+                ctx.print_color(
+                    x,
+                    self.view_height() + 1 + index as i32,
+                    color,
+                    RGB::named(BLACK),
+                    format!(
+                        "{} {} {}",
+                        kind.letter(),
+                        kind.name(),
+                        cost_label(kind.cost())
+                    ),
+                );
+            }
+        } else if let Some((kind, queue, progress)) = self.selected_production() {
+            let y = self.view_height() + 1;
+            ctx.print_color(
+                x,
+                y,
+                RGB::named(WHITE),
+                RGB::named(BLACK),
+                format!("{} {}/{}", kind.name(), queue.len(), QUEUE_SIZE),
+            );
+            for (index, unit) in queue.iter().enumerate() {
+                ctx.print_color(
+                    x + kind.name().len() as i32 + 5 + index as i32,
+                    y,
+                    unit.color(),
+                    RGB::named(BLACK),
+                    &unit.symbol().to_string(),
+                );
+            }
+            if !queue.is_empty() {
+                ctx.draw_bar_horizontal(
+                    x,
+                    y + 1,
+                    20,
+                    (progress * 100.0) as i32,
+                    100,
+                    RGB::named(GREEN),
+                    RGB::named(DARK_GRAY),
+                );
+            }
+            let hints = ["Q", "W", "E"]
+                .iter()
+                .zip(kind.trains().iter())
+                .map(|(key, unit)| format!("{} {} {}", key, unit.name(), cost_label(unit.cost())))
+                .collect::<Vec<String>>()
+                .join("  ");
+            ctx.print_color(
+                x,
+                y + 2,
+                RGB::named(WHITE),
+                RGB::named(BLACK),
+                format!("{}  C Cancel", hints),
+            );
+        } else {
+            self.print_selection(ctx);
+        }
+
+        self.print_command_card(ctx);
+
+        if self.mouse.y() >= self.view_height() {
+            self.mouse.print_cursor(ctx, self.mode, self.tic);
+        }
+    }
+
+    /// Return the buttons on the command card for the current mode and selection, laid out in
+    /// rows of three
+    fn command_card(&self) -> [Option<Button>; 9] {
+        let mut card = [None; 9];
+        if let Mode::Build = self.mode {
+            for (slot, kind) in self.local_info().race().buildings().iter().enumerate() {
+                card[slot] = Some(Button::Place(*kind));
+            }
+            card[8] = Some(Button::Back);
+        } else if let Some((kind, _, _)) = self.selected_production() {
+            for (slot, unit) in kind.trains().iter().enumerate().take(3) {
+                card[slot] = Some(Button::Train(slot, *unit));
+            }
+            card[6] = Some(Button::Cancel);
+        } else {
+            card[0] = Some(Button::Move);
+            card[1] = Some(Button::Stop);
+            card[2] = Some(Button::Attack);
+            card[4] = Some(Button::Hold);
+            card[6] = Some(Button::Build);
+        }
+        card
+    }
+
+    /// Return why the button can't be used right now, if it can't
+    fn disabled_reason(&self, button: Button) -> Option<&'static str> {
+        let info = self.local_info();
+        let short = |cost: (u32, u32)| {
+            if info.minerals() < cost.0 {
+                Some("Not enough minerals")
+            } else if info.gas() < cost.1 {
+                Some("Not enough gas")
+            } else {
+                None
+            }
+        };
+        match button {
+            Button::Move | Button::Stop | Button::Attack | Button::Hold => {
+                let mut query = <(Read<GameCell>,)>::query().filter(component::<Unit>());
+                if query.iter(self.sim.world()).any(|(cell,)| cell.selected()) {
+                    None
+                } else {
+                    Some("No units selected")
+                }
+            }
+            Button::Train(_, kind) => {
+                let queue = self
+                    .selected_production()
+                    .map_or(0, |(_, queue, _)| queue.len());
+                if queue >= QUEUE_SIZE {
+                    Some("The queue is full")
+                } else if !info.has_supply(kind.supply()) {
+                    Some("Not enough supply")
+                } else {
+                    short(kind.cost())
+                }
+            }
+            Button::Cancel => {
+                if self
+                    .selected_production()
+                    .map_or(true, |(_, queue, _)| queue.is_empty())
+                {
+                    Some("Nothing is being trained")
+                } else {
+                    None
+                }
+            }
+            Button::Place(kind) => short(kind.cost()),
+            Button::Build | Button::Back => None,
+        }
+    }
+
+    /// Do what the button on the command card does, the same as pressing its hotkey
+    fn press(&mut self, button: Button) {
+        match button {
+            Button::Move => {
+                if !self.selected.is_empty() {
+                    self.set_mode(Mode::Move)
+                }
+            }
+            Button::Attack => {
+                if !self.selected.is_empty() {
+                    self.set_mode(Mode::Attack)
+                }
+            }
+            Button::Stop => self.stop_cells(),
+            Button::Hold => self.hold_cells(),
+            Button::Build => self.set_mode(Mode::Build),
+            Button::Train(slot, _) => self.train_unit(slot),
+            Button::Cancel => self.cancel_unit(),
+            Button::Place(kind) => self.placing = Some(kind),
+            Button::Back => {
+                self.placing = None;
+                self.set_mode(Mode::Select);
+            }
+        }
+    }
+
+    /// Return the slot of the command card under the screen point, if any
+    fn card_slot(&self, screen: Point) -> Option<usize> {
+        let x = screen.x - (self.window_size.0 as i32 - 4);
+        let y = screen.y - (self.window_size.1 as i32 - 4);
+        if (0..3).contains(&x) && (0..3).contains(&y) {
+            Some((y * 3 + x) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Draw the command card in the bottom right corner, greying out buttons that can't be used
+    /// and describing the one under the mouse
+    fn print_command_card(&self, ctx: &mut BTerm) {
         ctx.draw_box(
             self.window_size.0 - 5,
             self.window_size.1 - 5,
@@ -536,47 +1203,413 @@ impl State {
             RGB::named(BLACK),
         );
 
-        let commands = [
-            (GREEN, "M"),
-            (YELLOW, "S"),
-            (RED, "A"),
-            (BLACK, ""),
-            (ORANGE, "H"),
-            (BLACK, ""),
-            (BLUE, "B"),
-        ];
-        for (index, (color, letter)) in commands.iter().enumerate() {
+        let hovered = self.card_slot(self.mouse.point);
+        for (slot, button) in self.command_card().iter().enumerate() {
+            let button = match button {
+                Some(button) => *button,
+                None => continue,
+            };
+            let reason = self.disabled_reason(button);
+            let color = if reason.is_some() {
+                RGB::named(DARK_GRAY)
+            } else {
+                button.color()
+            };
             ctx.print_color(
-                self.window_size.0 as usize - 4 + index % 3,
-                self.window_size.1 as usize - 4 + index / 3,
-                RGB::named(*color),
+                self.window_size.0 as i32 - 4 + slot as i32 % 3,
+                self.window_size.1 as i32 - 4 + slot as i32 / 3,
+                color,
                 RGB::named(BLACK),
-                letter,
+                &button.symbol().to_string(),
             );
+
+            if hovered == Some(slot) {
+                let mut text = button.label();
+                if let Some(cost) = button.cost() {
+                    text = format!("{} {}", text, cost_label(cost));
+                }
+                if let Some(reason) = reason {
+                    text = format!("{} - {}", text, reason);
+                }
+                ctx.print_color(
+                    self.window_size.0 as i32 - text.len() as i32 - 1,
+                    self.window_size.1 as i32 - 6,
+                    color,
+                    RGB::named(BLACK),
+                    &text,
+                );
+            }
         }
+    }
 
-        if self.mouse.y() > self.window_size.1 as i32 - 5 {
-            self.mouse.print_cursor(ctx, self.mode, self.tic);
+    /// Return the selected units in the order they were selected, along with what they are doing
+    fn selected_units(&self) -> Vec<(Entity, Unit, Mode)> {
+        self.selected
+            .iter()
+            .filter_map(|e| {
+                let entry = self.sim.world().entry_ref(*e).ok()?;
+                let unit = entry.get_component::<Unit>().ok()?.clone();
+                let mode = entry.get_component::<GameCell>().ok()?.mode();
+                Some((*e, unit, mode))
+            })
+            .collect()
+    }
+
+    /// Return the screen area of the selection panel, between the minimap and the command card
+    fn panel_rect(&self) -> Rect {
+        let x = MINIMAP_SIZE.0 + 3;
+        Rect::with_size(
+            x,
+            self.view_height() + 1,
+            self.window_size.0 as i32 - 7 - x,
+            BAR_HEIGHT - 2,
+        )
+    }
+
+    /// Return how many of the selected units have a tile on the selection panel. When they don't
+    /// all fit, the last row is left for the count of those without one.
+    fn panel_shown(&self, units: usize) -> usize {
+        let rect = self.panel_rect();
+        let columns = (rect.width() / 2).max(1) as usize;
+        let rows = rect.height().max(1) as usize;
+        if units > columns * rows {
+            columns * (rows - 1)
+        } else {
+            units
+        }
+    }
+
+    /// Return the index in the selected units of the panel tile under the screen point, if any
+    fn panel_slot(&self, screen: Point) -> Option<usize> {
+        if let Mode::Build = self.mode {
+            return None;
+        }
+        let rect = self.panel_rect();
+        if !rect.point_in_rect(screen) || (screen.x - rect.x1) % 2 == 1 {
+            return None;
+        }
+        let slot = ((screen.y - rect.y1) * (rect.width() / 2) + (screen.x - rect.x1) / 2) as usize;
+        let units = self.selected_units();
+        if units.len() > 1
+            && slot < self.panel_shown(units.len())
+            && self.selected_production().is_none()
+        {
+            Some(slot)
+        } else {
+            None
+        }
+    }
+
+    /// Select only the given entity
+    fn select_only(&mut self, e: Entity) {
+        let mut query = <(Write<GameCell>,)>::query();
+        for chunk in query.iter_chunks_mut(self.sim.world_mut()) {
+            for (entity, (cell,)) in chunk.into_iter_entities() {
+                if entity == e {
+                    cell.select();
+                } else {
+                    cell.deselect();
+                }
+            }
+        }
+        self.selected = vec![e];
+        self.sync_selection();
+    }
+
+    /// Draw the details of a single selected unit, or a tile for each of several coloured by
+    /// how much of their HP they have left
+    fn print_selection(&self, ctx: &mut BTerm) {
+        let rect = self.panel_rect();
+        let units = self.selected_units();
+        match units.as_slice() {
+            [] => (),
+            [(_, unit, mode)] => {
+                let kind = unit.kind();
+                let hp = unit.hp().max(0) as f32 / unit.max_hp() as f32;
+                ctx.print_color(
+                    rect.x1,
+                    rect.y1,
+                    kind.color(),
+                    RGB::named(BLACK),
+                    format!("{} {} ({})", kind.symbol(), kind.name(), unit.race().name()),
+                );
+                ctx.print_color(
+                    rect.x1,
+                    rect.y1 + 1,
+                    hp_color(hp),
+                    RGB::named(BLACK),
+                    format!("HP {}/{}", unit.hp().max(0), unit.max_hp()),
+                );
+                ctx.print_color(
+                    rect.x1 + 12,
+                    rect.y1 + 1,
+                    RGB::named(WHITE),
+                    RGB::named(BLACK),
+                    format!(
+                        "Damage {}  Range {}  Speed {:.1}",
+                        unit.damage(),
+                        unit.range(),
+                        unit.speed()
+                    ),
+                );
+                ctx.print_color(
+                    rect.x1,
+                    rect.y1 + 2,
+                    RGB::named(WHITE),
+                    RGB::named(BLACK),
+                    mode.status(),
+                );
+            }
+            units => {
+                let columns = (rect.width() / 2).max(1) as usize;
+                let shown = self.panel_shown(units.len());
+                let hovered = self.panel_slot(self.mouse.point);
+                for (slot, (_, unit, _)) in units.iter().enumerate().take(shown) {
+                    let hp = unit.hp().max(0) as f32 / unit.max_hp() as f32;
+                    let bg = if hovered == Some(slot) {
+                        RGB::named(DARK_GRAY)
+                    } else {
+                        RGB::named(BLACK)
+                    };
+                    ctx.print_color(
+                        rect.x1 + (slot % columns) as i32 * 2,
+                        rect.y1 + (slot / columns) as i32,
+                        hp_color(hp),
+                        bg,
+                        &unit.kind().symbol().to_string(),
+                    );
+                }
+                if units.len() > shown {
+                    let more = format!("+{}", units.len() - shown);
+                    ctx.print_color(
+                        rect.x2 - more.len() as i32,
+                        rect.y2 - 1,
+                        RGB::named(WHITE),
+                        RGB::named(BLACK),
+                        &more,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Return the screen area inside the minimap's border
+    fn minimap_rect(&self) -> Rect {
+        Rect::with_size(1, self.view_height() + 1, MINIMAP_SIZE.0, MINIMAP_SIZE.1)
+    }
+
+    /// Return the world point shown at the screen point if it is on the minimap
+    fn minimap_point(&self, screen: Point) -> Option<Point> {
+        let rect = self.minimap_rect();
+        if !rect.point_in_rect(screen) {
+            return None;
         }
+        let map = self.sim.resources().get::<Map>().unwrap();
+        let (w, h) = MINIMAP_SIZE;
+        let (i, j) = (screen.x - rect.x1, screen.y - rect.y1);
+        Some(Point::new(
+            map.lower_x() + (2 * i + 1) * map.width() / (2 * w),
+            map.lower_y() + (2 * j + 1) * map.height() / (2 * h),
+        ))
     }
 
-    fn render_cells(&mut self, ctx: &mut BTerm) {
-        let mut query = <(Write<GameCell>, Write<Unit>)>::query();
+    /// Draw the whole map scaled down into the bottom left corner, with a dot for every unit and
+    /// building coloured by its owner and the area the camera shows outlined
+    fn print_minimap(&self, ctx: &mut BTerm) {
+        let (w, h) = MINIMAP_SIZE;
+        let rect = self.minimap_rect();
+        ctx.draw_box(
+            0,
+            rect.y1 - 1,
+            w + 1,
+            h + 1,
+            RGB::named(WHITE),
+            RGB::named(BLACK),
+        );
 
-        for (cell, unit) in query.iter_mut(&mut self.world) {
+        let team = self.fog_team();
+        let map = self.sim.resources().get::<Map>().unwrap();
+        let vision = self.sim.resources().get::<Vision>().unwrap();
+        let players = self.sim.resources().get::<Players>().unwrap();
+        let minimap_cell = |point: Point| {
+            let i = (point.x - map.lower_x()) * w / map.width();
+            let j = (point.y - map.lower_y()) * h / map.height();
+            if i >= 0 && i < w && j >= 0 && j < h {
+                Some((j * w + i) as usize)
+            } else {
+                None
+            }
+        };
+
+        let mut dots = vec![None; (w * h) as usize];
+        let mut query = <(Read<GameCell>, Read<Player>)>::query();
+        for (cell, player) in query.iter(self.sim.world()) {
+            if let Some(team) = team {
+                if players.hostile(players.local(), *player)
+                    && !vision.any_visible(team, cell.footprint())
+                {
+                    continue;
+                }
+            }
+            if let (Some(idx), Some(info)) = (minimap_cell(cell.point()), players.get(*player)) {
+                dots[idx] = Some(info.color());
+            }
+        }
+
+        // the corners of the camera's view on the minimap
+        let view = (
+            minimap_cell(Point::new(-self.offset.0, -self.offset.1)),
+            minimap_cell(Point::new(
+                -self.offset.0 + self.window_size.0 as i32 - 1,
+                -self.offset.1 + self.view_height() - 1,
+            )),
+        );
+        let (top_left, bottom_right) = match view {
+            (Some(a), Some(b)) => ((a as i32 % w, a as i32 / w), (b as i32 % w, b as i32 / w)),
+            (Some(a), None) => ((a as i32 % w, a as i32 / w), (w - 1, h - 1)),
+            (None, Some(b)) => ((0, 0), (b as i32 % w, b as i32 / w)),
+            (None, None) => ((0, 0), (w - 1, h - 1)),
+        };
+
+        for j in 0..h {
+            for i in 0..w {
+                let point = Point::new(
+                    map.lower_x() + (2 * i + 1) * map.width() / (2 * w),
+                    map.lower_y() + (2 * j + 1) * map.height() / (2 * h),
+                );
+                let shade = match team {
+                    Some(team) if !vision.is_explored(team, point) => 0.0,
+                    Some(team) if !vision.is_visible(team, point) => 0.25,
+                    _ => 0.5,
+                };
+                let mut bg = map.tile(point).map_or(RGB::named(BLACK), |tile| {
+                    let color = tile.color();
+                    RGB::from_f32(color.r * shade, color.g * shade, color.b * shade)
+                });
+                let on_view = (i == top_left.0 || i == bottom_right.0)
+                    && j >= top_left.1
+                    && j <= bottom_right.1
+                    || (j == top_left.1 || j == bottom_right.1)
+                        && i >= top_left.0
+                        && i <= bottom_right.0;
+                if on_view {
+                    bg = RGB::named(DARK_GRAY);
+                }
+                match dots[(j * w + i) as usize] {
+                    Some(color) => ctx.print_color(rect.x1 + i, rect.y1 + j, color, bg, "•"),
+                    None => ctx.print_color(rect.x1 + i, rect.y1 + j, bg, bg, " "),
+                }
+            }
+        }
+    }
+
+    fn render_buildings(&mut self, ctx: &mut BTerm) {
+        let team = self.fog_team();
+        let players = self.sim.resources().get::<Players>().unwrap();
+        let vision = self.sim.resources().get::<Vision>().unwrap();
+        let mut supply_blocked = false;
+        let mut query = <(Read<GameCell>, Read<Building>, Read<Player>)>::query();
+
+        let mouse = Point::new(
+            self.mouse.x() - self.offset.0,
+            self.mouse.y() - self.offset.1,
+        );
+        for (cell, building, player) in query.iter(self.sim.world()) {
+            if let Some(team) = team {
+                if players.hostile(players.local(), *player)
+                    && !vision.any_visible(team, cell.footprint())
+                {
+                    continue;
+                }
+            }
+            let color = if cell.contains(mouse) {
+                cell.color_bright()
+            } else {
+                cell.color()
+            };
+            let color = if building.is_constructed() {
+                color
+            } else {
+                RGB::from_f32(color.r * 0.5, color.g * 0.5, color.b * 0.5)
+            };
+            for (dy, row) in building.kind().art().iter().enumerate() {
+                for (dx, symbol) in row.chars().enumerate() {
+                    let x = cell.x() + dx as i32 + self.offset.0;
+                    let y = cell.y() + dy as i32 + self.offset.1;
+                    if x >= 0
+                        && y >= 0
+                        && x < self.window_size.0 as i32
+                        && y < self.window_size.1 as i32
+                    {
+                        ctx.print_color(x, y, color, cell.bg_color(), &symbol.to_string());
+                    }
+                }
+            }
+
+            // production pauses while the owner is over their supply cap
+            if !building.queue().is_empty()
+                && players.is_local(*player)
+                && !players
+                    .get(*player)
+                    .map_or(false, |info| info.has_supply(0))
+            {
+                supply_blocked = true;
+            }
+        }
+
+        drop(players);
+        drop(vision);
+        if supply_blocked {
+            self.warn("Not enough supply");
+        }
+    }
+
+    fn render_nodes(&self, ctx: &mut BTerm) {
+        let team = self.fog_team();
+        let vision = self.sim.resources().get::<Vision>().unwrap();
+        let mut query = <(Read<GameCell>, Read<ResourceNode>)>::query();
+        for (cell, _) in query.iter(self.sim.world()) {
+            if team.map_or(false, |team| !vision.is_explored(team, cell.point())) {
+                continue;
+            }
+            let x = cell.x() + self.offset.0;
+            let y = cell.y() + self.offset.1;
+            if x >= 0 && y >= 0 && x < self.window_size.0 as i32 && y < self.window_size.1 as i32 {
+                ctx.print_color(x, y, cell.color(), RGB::new(), &cell.symbol().to_string());
+            }
+        }
+    }
+
+    fn render_cells(&self, ctx: &mut BTerm) {
+        let alpha = self.accumulator / TICK_SECONDS;
+        let team = self.fog_team();
+        let players = self.sim.resources().get::<Players>().unwrap();
+        let vision = self.sim.resources().get::<Vision>().unwrap();
+        let mut query = <(Read<GameCell>, Read<Player>)>::query().filter(component::<Unit>());
+
+        for (cell, player) in query.iter(self.sim.world()) {
+            // enemies are only shown while the local player's team can see them
+            if let Some(team) = team {
+                if players.hostile(players.local(), *player)
+                    && !vision.is_visible(team, cell.point())
+                {
+                    continue;
+                }
+            }
+            let point = cell.render_point(alpha);
             if Rect::with_exact(
                 -self.offset.0,
                 -self.offset.1,
                 self.window_size.0 as i32 - self.offset.0,
                 self.window_size.1 as i32 - self.offset.1,
             )
-            .point_in_rect(cell.point())
+            .point_in_rect(point)
             {
                 ctx.print_color(
-                    cell.x() + self.offset.0,
-                    cell.y() + self.offset.1,
-                    if self.mouse.x() - self.offset.0 == cell.x()
-                        && self.mouse.y() - self.offset.1 == cell.y()
+                    point.x + self.offset.0,
+                    point.y + self.offset.1,
+                    if self.mouse.x() - self.offset.0 == point.x
+                        && self.mouse.y() - self.offset.1 == point.y
                     {
                         cell.color_bright()
                     } else {
@@ -586,16 +1619,206 @@ impl State {
                     &cell.symbol().to_string(),
                 );
             }
+        }
+    }
+
+    /// Keep the damage and deaths of the tick just run to be shown, if the overlay is on
+    fn take_effects(&mut self) {
+        let effects = self.sim.take_effects();
+        if self.overlay {
+            self.effects
+                .extend(effects.into_iter().map(|effect| (effect, 0.0)));
+        }
+    }
+
+    /// Draw health bars over damaged units, damage numbers rising from whatever was hit, and
+    /// units fading away where they died, unless the overlay is turned off
+    fn print_overlay(&mut self, ctx: &mut BTerm) {
+        let dt = self.dt;
+        self.effects.retain(|(_, age)| *age + dt < EFFECT_SECONDS);
+        for (_, age) in self.effects.iter_mut() {
+            *age += dt;
+        }
+        if !self.overlay {
+            return;
+        }
+
+        let team = self.fog_team();
+        let vision = self.sim.resources().get::<Vision>().unwrap();
+        let view = Rect::with_size(0, 0, self.window_size.0 as i32, self.view_height());
+        // effects are drawn the given number of tiles above points that can be seen
+        let on_screen = |point: Point, rise: i32| {
+            let screen = Point::new(point.x + self.offset.0, point.y + self.offset.1 - rise);
+            if view.point_in_rect(screen)
+                && team.map_or(true, |team| vision.is_visible(team, point))
+            {
+                Some(screen)
+            } else {
+                None
+            }
+        };
+
+        // the bar's shade shows how much HP is left and its color how urgent it is
+        let alpha = self.accumulator / TICK_SECONDS;
+        let mut query = <(Read<GameCell>, Read<Unit>)>::query();
+        for (cell, unit) in query.iter(self.sim.world()) {
+            if unit.hp() >= unit.max_hp() as i32 {
+                continue;
+            }
+            let point = cell.render_point(alpha);
+            if let Some(screen) = on_screen(point, 1) {
+                let hp = unit.hp().max(0) as f32 / unit.max_hp() as f32;
+                let bar = ['░', '▒', '▓', '█'][((hp * 4.0) as usize).min(3)];
+                ctx.print_color(
+                    screen.x,
+                    screen.y,
+                    hp_color(hp),
+                    RGB::named(BLACK),
+                    &bar.to_string(),
+                );
+            }
+        }
+
+        for (effect, age) in self.effects.iter() {
+            let t = age / EFFECT_SECONDS;
+            match *effect {
+                Effect::Damage(point, damage) => {
+                    let rise = (t * 3.0) as i32 + 1;
+                    if let Some(screen) = on_screen(point, rise) {
+                        ctx.print_color(
+                            screen.x,
+                            screen.y,
+                            RGB::from_f32(1.0, 1.0 - t, 0.0),
+                            RGB::named(BLACK),
+                            &format!("-{}", damage),
+                        );
+                    }
+                }
+                Effect::Death(point, symbol, color) => {
+                    if let Some(screen) = on_screen(point, 0) {
+                        let glyph = match (t * 4.0) as usize {
+                            0 => symbol,
+                            1 => '*',
+                            2 => '+',
+                            _ => '.',
+                        };
+                        let fade = 1.0 - t;
+                        ctx.print_color(
+                            screen.x,
+                            screen.y,
+                            RGB::from_f32(color.r * fade, color.g * fade, color.b * fade),
+                            RGB::named(BLACK),
+                            &glyph.to_string(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return the kind, queue and progress of the first selected production building
+    fn selected_production(&self) -> Option<(BuildingKind, Vec<UnitKind>, f32)> {
+        let mut query = <(Read<GameCell>, Read<Building>)>::query();
+        query
+            .iter(self.sim.world())
+            .find(|(cell, building)| {
+                cell.selected() && building.is_constructed() && !building.kind().trains().is_empty()
+            })
+            .map(|(_, building)| {
+                (
+                    building.kind(),
+                    building.queue().to_vec(),
+                    building.progress(),
+                )
+            })
+    }
+
+    fn local_player(&self) -> Player {
+        self.sim.resources().get::<Players>().unwrap().local()
+    }
+    fn local_info(&self) -> PlayerInfo {
+        let players = self.sim.resources().get::<Players>().unwrap();
+        players.get(players.local()).unwrap().clone()
+    }
+
+    /// Return the top left corner of a building footprint centered on the mouse
+    fn placement_origin(&self, kind: BuildingKind) -> Point {
+        let (w, h) = kind.size();
+        Point::new(
+            self.mouse.x() - self.offset.0 - w / 2,
+            self.mouse.y() - self.offset.1 - h / 2,
+        )
+    }
+
+    /// Return whether a building's footprint would only cover walkable tiles with no units on them
+    fn placement_valid(&self, kind: BuildingKind, origin: Point) -> bool {
+        let (w, h) = kind.size();
+        let footprint = Rect::with_size(origin.x, origin.y, w, h);
+
+        let mut query = <(Read<GameCell>,)>::query().filter(component::<Unit>());
+        self.sim
+            .resources()
+            .get::<Map>()
+            .unwrap()
+            .is_clear(footprint)
+            && !query
+                .iter(self.sim.world())
+                .any(|(cell,)| footprint.point_in_rect(cell.point()))
+    }
+
+    /// Issue a command on behalf of the local player
+    fn command(&mut self, command: Command) {
+        let local = self.sim.resources().get::<Players>().unwrap().local();
+        self.sim.command(local, command);
+    }
+
+    /// Hand the current selection to the simulation so later commands act on it
+    fn sync_selection(&mut self) {
+        self.command(Command::Select(self.selected.clone()));
+    }
+
+    fn place_building(&mut self) {
+        let kind = match self.placing {
+            Some(kind) => kind,
+            None => return,
+        };
+        // a click on the bottom bar is never a placement
+        if self.mouse.y() >= self.view_height() {
+            return;
+        }
+        let origin = self.placement_origin(kind);
+        if !self.placement_valid(kind, origin) || !self.local_info().can_afford(kind.cost()) {
+            return;
+        }
 
-            cell.update(self.dt, unit.speed());
-            unit.tic(self.dt);
+        self.command(Command::Build(kind, origin));
+        self.placing = None;
+        self.set_mode(Mode::Select);
+    }
+
+    /// Queue the unit in the given slot of the selected production building with the shortest queue
+    fn train_unit(&mut self, slot: usize) {
+        let unit = self
+            .selected_production()
+            .and_then(|(kind, _, _)| kind.trains().get(slot).copied());
+        if let Some(unit) = unit {
+            if !self.local_info().has_supply(unit.supply()) {
+                self.warn("Not enough supply");
+            } else {
+                self.command(Command::Train(unit));
+            }
         }
     }
 
+    /// Cancel the last queued unit of the selected production building and refund its cost
+    fn cancel_unit(&mut self) {
+        self.command(Command::Cancel);
+    }
+
     fn load_ctrl_group(&mut self) {
         let mut query = <(Write<GameCell>,)>::query();
 
-        for chunk in query.iter_chunks_mut(&mut self.world) {
+        for chunk in query.iter_chunks_mut(self.sim.world_mut()) {
             for (e, (cell,)) in chunk.into_iter_entities() {
                 if self.selected.contains(&e) {
                     cell.select();
@@ -604,57 +1827,68 @@ impl State {
                 }
             }
         }
+        self.sync_selection();
     }
 
     fn select_cells(&mut self) {
-        let mut query = <(Write<GameCell>,)>::query();
+        let local = self.sim.resources().get::<Players>().unwrap().local();
+        let mouse = Point::new(
+            self.mouse.x() - self.offset.0,
+            self.mouse.y() - self.offset.1,
+        );
 
         if self.mode != Mode::Add {
             self.selected = Vec::new();
+
+            let mut query = <(Write<GameCell>,)>::query();
+            for (cell,) in query.iter_mut(self.sim.world_mut()) {
+                cell.deselect();
+            }
         }
 
         if self.mouse.select_one() {
-            for chunk in query.iter_chunks_mut(&mut self.world) {
-                for (e, (cell,)) in chunk.into_iter_entities() {
-                    if self.mouse.x() == cell.x() + self.offset.0
-                        && self.mouse.y() == cell.y() + self.offset.1
-                    {
+            let mut query = <(Write<GameCell>, Read<Player>)>::query();
+            for chunk in query.iter_chunks_mut(self.sim.world_mut()) {
+                for (e, (cell, player)) in chunk.into_iter_entities() {
+                    if *player == local && cell.contains(mouse) {
                         cell.select();
                         self.selected.push(e);
-                    } else if self.mode != Mode::Add {
-                        cell.deselect();
                     }
                 }
             }
         } else {
-            for chunk in query.iter_chunks_mut(&mut self.world) {
-                for (e, (cell,)) in chunk.into_iter_entities() {
-                    if self
-                        .mouse
-                        .point_in_selection(cell.x() + self.offset.0, cell.y() + self.offset.1)
+            // box selections only pick up units, never buildings
+            let mut query = <(Write<GameCell>, Read<Player>)>::query().filter(component::<Unit>());
+            for chunk in query.iter_chunks_mut(self.sim.world_mut()) {
+                for (e, (cell, player)) in chunk.into_iter_entities() {
+                    if *player == local
+                        && self
+                            .mouse
+                            .point_in_selection(cell.x() + self.offset.0, cell.y() + self.offset.1)
                     {
                         cell.select();
                         self.selected.push(e);
-                    } else if self.mode != Mode::Add {
-                        cell.deselect();
                     }
                 }
             }
         }
 
+        self.sync_selection();
         self.mode = Mode::Select;
     }
 
     fn select_same(&mut self) {
-        let mut query = <(Write<GameCell>, Read<Unit>)>::query();
+        let local = self.sim.resources().get::<Players>().unwrap().local();
+        let mut query = <(Write<GameCell>, Read<Unit>, Read<Player>)>::query();
 
         self.selected = Vec::new();
 
         let mut kind = None;
 
         if self.mouse.select_one() {
-            for (cell, unit) in query.iter_mut(&mut self.world) {
-                if self.mouse.x() == cell.x() + self.offset.0
+            for (cell, unit, player) in query.iter_mut(self.sim.world_mut()) {
+                if *player == local
+                    && self.mouse.x() == cell.x() + self.offset.0
                     && self.mouse.y() == cell.y() + self.offset.1
                 {
                     kind = Some(unit.kind());
@@ -663,13 +1897,14 @@ impl State {
             }
         }
         if let Some(kind) = kind {
-            for chunk in query.iter_chunks_mut(&mut self.world) {
-                for (e, (cell, unit)) in chunk.into_iter_entities() {
-                    if kind == unit.kind()
+            for chunk in query.iter_chunks_mut(self.sim.world_mut()) {
+                for (e, (cell, unit, player)) in chunk.into_iter_entities() {
+                    if *player == local
+                        && kind == unit.kind()
                         && cell.x() + self.offset.0 > 0
                         && cell.y() + self.offset.1 > 0
                         && cell.x() + self.offset.0 < self.window_size.0 as i32
-                        && cell.y() + self.offset.1 < self.window_size.1 as i32
+                        && cell.y() + self.offset.1 < self.view_height()
                     {
                         cell.select();
                         self.selected.push(e);
@@ -680,75 +1915,114 @@ impl State {
             }
         }
 
+        self.sync_selection();
         self.mode = Mode::Select;
     }
 
-    fn move_cells(&mut self, mode: Mode) {
-        let mut query = <(Write<GameCell>, Write<Unit>)>::query();
-
-        for (cell, _) in query.iter_mut(&mut self.world) {
-            if cell.selected() {
-                cell.move_pos(
-                    Point::new(
-                        self.mouse.x() - self.offset.0,
-                        self.mouse.y() - self.offset.1,
-                    ),
-                    mode,
-                );
+    /// Set the rally of the selected production buildings to the friendly unit under the mouse,
+    /// or otherwise to the point under the mouse
+    fn rally_buildings(&mut self, mode: Mode) {
+        let point = Point::new(
+            self.mouse.x() - self.offset.0,
+            self.mouse.y() - self.offset.1,
+        );
+        let players = self.sim.resources().get::<Players>().unwrap();
+        let local = players.local();
+
+        let mut rally = Rally::Point(point, mode);
+        let mut unit_query = <(Read<GameCell>, Read<Player>)>::query().filter(component::<Unit>());
+        for chunk in unit_query.iter_chunks(self.sim.world()) {
+            for (e, (cell, player)) in chunk.into_iter_entities() {
+                if cell.contains(point) && !players.hostile(local, *player) {
+                    rally = Rally::Unit(e);
+                }
             }
         }
+        drop(players);
+
+        self.command(Command::Rally(rally));
     }
 
-    fn stop_cells(&mut self) {
-        let mut query = <(Write<GameCell>, Write<Unit>)>::query();
+    /// Send the selected workers to gather from the resource node under the mouse
+    fn gather_cells(&mut self) {
+        let point = Point::new(
+            self.mouse.x() - self.offset.0,
+            self.mouse.y() - self.offset.1,
+        );
 
-        for (cell, _) in query.iter_mut(&mut self.world) {
-            if cell.selected() {
-                cell.stop_moving();
+        let mut node = None;
+        let mut query = <(Read<GameCell>,)>::query().filter(component::<ResourceNode>());
+        for chunk in query.iter_chunks(self.sim.world()) {
+            for (e, (cell,)) in chunk.into_iter_entities() {
+                if cell.contains(point) {
+                    node = Some(e);
+                }
             }
         }
+
+        if let Some(node) = node {
+            self.command(Command::Gather(node));
+        }
+    }
+
+    fn move_cells(&mut self, mode: Mode) {
+        let dest = Point::new(
+            self.mouse.x() - self.offset.0,
+            self.mouse.y() - self.offset.1,
+        );
+        self.command(Command::Move(dest, mode));
+    }
+
+    fn stop_cells(&mut self) {
+        self.command(Command::Stop);
     }
 
     fn hold_cells(&mut self) {
-        let mut query = <(Write<GameCell>, Write<Unit>)>::query();
+        self.command(Command::Hold);
+    }
 
-        for (cell, _) in query.iter_mut(&mut self.world) {
+    fn focus_cell(&mut self) {
+        let mut focus = None;
+        let mut query = <(Read<GameCell>,)>::query().filter(component::<Unit>());
+        for (cell,) in query.iter(self.sim.world()) {
             if cell.selected() {
-                cell.hold();
+                focus = Some(cell.point());
             }
         }
+        if let Some(point) = focus {
+            self.center_camera(point);
+        }
     }
 
-    fn focus_cell(&mut self) {
-        let mut query = <(Write<GameCell>, Write<Unit>)>::query();
-        for (cell, _) in query.iter_mut(&mut self.world) {
-            if cell.selected() {
-                let x = -cell.x() + self.window_size.0 as i32 / 2;
-                let x = if x < self.map.lower_x() {
-                    self.map.lower_x()
-                } else if x > self.map.upper_x() {
-                    self.map.upper_x()
-                } else {
-                    x
-                };
-                let y = -cell.y() + self.window_size.1 as i32 / 2;
-                let y = if y < self.map.lower_y() {
-                    self.map.lower_y()
-                } else if y > self.map.upper_y() {
-                    self.map.upper_y()
-                } else {
-                    y
-                };
+    /// Move the camera so the point is in the middle of the screen, as far as the map allows
+    fn center_camera(&mut self, point: Point) {
+        let map = self.sim.resources().get::<Map>().unwrap();
+        let x = -point.x + self.window_size.0 as i32 / 2;
+        let x = if x < map.lower_x() {
+            map.lower_x()
+        } else if x > map.upper_x() {
+            map.upper_x()
+        } else {
+            x
+        };
+        let y = -point.y + self.view_height() / 2;
+        let y = if y < map.lower_y() {
+            map.lower_y()
+        } else if y > map.upper_y() {
+            map.upper_y()
+        } else {
+            y
+        };
 
-                self.offset = (x, y);
-            }
-        }
+        self.offset = (x, y);
     }
 
     fn quit_state(&mut self, ctx: &mut BTerm) {
         ctx.print(5, 5, "Are you sure you want to quit? (y/n)");
 
         if let Some(VirtualKeyCode::Y) = ctx.key {
+            // the game is closing either way, so a replay that can't be written is simply lost
+            let _ = self.sim.replay().save(REPLAY_PATH);
             ctx.quit();
         } else if let Some(VirtualKeyCode::N) = ctx.key {
             self.curr_state = CurrentState::Playing;
@@ -774,7 +2048,8 @@ impl State {
                 self.mouse.pressed = (button, false, self.mouse.pressed.1)
             }
             BEvent::MouseButtonDown { button } => {
-                self.mouse.pressed = (button, true, self.mouse.pressed.1)
+                self.mouse.pressed = (button, true, self.mouse.pressed.1);
+                self.mouse.down = self.mouse.point;
             }
             _ => (),
         });
@@ -790,7 +2065,10 @@ impl State {
         input.for_each_message(|event| match event {
             BEvent::MouseClick { button, pressed } => self.mouse.click = Some((button, pressed)),
             BEvent::MouseButtonUp { button } => self.mouse.pressed = (button, false, false),
-            BEvent::MouseButtonDown { button } => self.mouse.pressed = (button, true, false),
+            BEvent::MouseButtonDown { button } => {
+                self.mouse.pressed = (button, true, false);
+                self.mouse.down = self.mouse.point;
+            }
             _ => (),
         });
     }
@@ -802,6 +2080,8 @@ impl GameState for State {
 
         ctx.cls();
 
+        self.mouse.point = ctx.mouse_point();
+
         self.get_input();
 
         self.tic += 4;
@@ -809,11 +2089,12 @@ impl GameState for State {
             self.tic = 0;
         }
 
-        self.mouse.point = ctx.mouse_point();
-
         match self.curr_state {
             CurrentState::Menu => self.menu_state(ctx),
             CurrentState::Playing => self.play_state(ctx),
+            CurrentState::Paused => self.pause_state(ctx),
+            CurrentState::Finished => self.finished_state(ctx),
+            CurrentState::Replay => self.replay_state(ctx),
             CurrentState::Quitting => self.quit_state(ctx),
         }
 
@@ -0,0 +1,76 @@
+use bracket_lib::prelude::*;
+
+use crate::types::{BuildingKind, UnitKind};
+
+/// A button on the command card, doing the same as one of the hotkeys
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Button {
+    Move,
+    Stop,
+    Attack,
+    Hold,
+    Build,
+    /// Train the unit in the given slot of the selected production building
+    Train(usize, UnitKind),
+    Cancel,
+    /// Pick the building to place from the build menu
+    Place(BuildingKind),
+    /// Leave the build menu
+    Back,
+}
+
+impl Button {
+    pub fn symbol(self) -> char {
+        match self {
+            Button::Move => 'M',
+            Button::Stop => 'S',
+            Button::Attack => 'A',
+            Button::Hold => 'H',
+            Button::Build => 'B',
+            Button::Train(_, kind) => kind.symbol(),
+            Button::Cancel => 'C',
+            Button::Place(kind) => kind.letter(),
+            Button::Back => '<',
+        }
+    }
+
+    pub fn color(self) -> RGB {
+        match self {
+            Button::Move => RGB::named(GREEN),
+            Button::Stop => RGB::named(YELLOW),
+            Button::Attack => RGB::named(RED),
+            Button::Hold => RGB::named(ORANGE),
+            Button::Build => RGB::named(BLUE),
+            Button::Train(_, kind) => kind.color(),
+            Button::Cancel | Button::Place(_) | Button::Back => RGB::named(WHITE),
+        }
+    }
+
+    /// Return what the button does along with the key that does the same
+    pub fn label(self) -> String {
+        match self {
+            Button::Move => "Move (M)".to_string(),
+            Button::Stop => "Stop (S)".to_string(),
+            Button::Attack => "Attack move (A)".to_string(),
+            Button::Hold => "Hold position (H)".to_string(),
+            Button::Build => "Build (B)".to_string(),
+            Button::Train(slot, kind) => format!(
+                "Train {} ({})",
+                kind.name(),
+                ["Q", "W", "E"].get(slot).unwrap_or(&"")
+            ),
+            Button::Cancel => "Cancel last in queue (C)".to_string(),
+            Button::Place(kind) => format!("Place {} ({})", kind.name(), kind.letter()),
+            Button::Back => "Back (Esc)".to_string(),
+        }
+    }
+
+    /// Return the minerals and gas the button spends, if any
+    pub fn cost(self) -> Option<(u32, u32)> {
+        match self {
+            Button::Train(_, kind) => Some(kind.cost()),
+            Button::Place(kind) => Some(kind.cost()),
+            _ => None,
+        }
+    }
+}
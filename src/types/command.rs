@@ -0,0 +1,32 @@
+use bracket_lib::prelude::Point;
+
+use legion::Entity;
+
+use crate::{
+    components::Rally,
+    types::{BuildingKind, Mode, UnitKind},
+};
+
+/// A command given by a player to a Simulation, acting on their current selection the same way
+/// input does
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Replace the player's selection with the entities
+    Select(Vec<Entity>),
+    /// Move, or attack-move, the selected units to a point
+    Move(Point, Mode),
+    /// Attack a hostile entity with the selected units
+    Attack(Entity),
+    Hold,
+    Stop,
+    /// Send the selected workers to gather from a resource node
+    Gather(Entity),
+    /// Place a construction site with its top left corner at the point
+    Build(BuildingKind, Point),
+    /// Queue a unit in the selected production building with the shortest queue
+    Train(UnitKind),
+    /// Cancel the last unit queued in a selected production building
+    Cancel,
+    /// Set the rally of the selected production buildings
+    Rally(Rally),
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// A way for a team to win a match, as listed in a scenario
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    /// A team wins once every other team has lost all of its units and buildings
+    Elimination,
+    /// A team wins by keeping units in the area with no enemy units for the number of seconds
+    HoldPoint {
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        seconds: f32,
+    },
+    /// The team wins if it still has units or buildings once the number of seconds has passed
+    Survive { team: usize, seconds: f32 },
+}
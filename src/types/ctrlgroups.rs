@@ -1,6 +1,12 @@
 use legion::*;
 
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::save::entity_lists;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CtrlGroups {
+    #[serde(with = "entity_lists")]
     groups: Vec<Vec<Entity>>,
     camera: Vec<(i32, i32)>,
 }
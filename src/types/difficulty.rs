@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+
+/// How well the computer players play
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Insane,
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Insane => "Insane",
+        }
+    }
+
+    /// Return the next harder Difficulty, staying at the hardest
+    pub fn harder(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard | Difficulty::Insane => Difficulty::Insane,
+        }
+    }
+    /// Return the next easier Difficulty, staying at the easiest
+    pub fn easier(self) -> Self {
+        match self {
+            Difficulty::Easy | Difficulty::Normal => Difficulty::Easy,
+            Difficulty::Hard => Difficulty::Normal,
+            Difficulty::Insane => Difficulty::Hard,
+        }
+    }
+
+    /// Return the number of seconds between each decision a computer player makes
+    pub fn reaction_time(self) -> f32 {
+        match self {
+            Difficulty::Easy => 3.0,
+            Difficulty::Normal => 1.5,
+            Difficulty::Hard => 1.0,
+            Difficulty::Insane => 0.5,
+        }
+    }
+
+    /// Return the most workers a computer player trains
+    pub fn max_workers(self) -> usize {
+        match self {
+            Difficulty::Easy => 8,
+            Difficulty::Normal => 12,
+            Difficulty::Hard => 16,
+            Difficulty::Insane => 20,
+        }
+    }
+    /// Return the most production buildings a computer player builds
+    pub fn max_production(self) -> usize {
+        match self {
+            Difficulty::Easy => 1,
+            Difficulty::Normal => 2,
+            Difficulty::Hard => 3,
+            Difficulty::Insane => 4,
+        }
+    }
+    /// Return the most units a computer player keeps queued in each production building
+    pub fn max_queued(self) -> usize {
+        match self {
+            Difficulty::Easy | Difficulty::Normal => 1,
+            Difficulty::Hard => 2,
+            Difficulty::Insane => 3,
+        }
+    }
+    /// Return how close to its supply cap a computer player gets before building more supply
+    pub fn supply_margin(self) -> i32 {
+        match self {
+            Difficulty::Easy => 1,
+            Difficulty::Normal => 4,
+            Difficulty::Hard => 6,
+            Difficulty::Insane => 8,
+        }
+    }
+
+    /// Return whether army units focus their attacks on the weakest enemy nearby
+    pub fn focus_fire(self) -> bool {
+        matches!(self, Difficulty::Hard | Difficulty::Insane)
+    }
+    /// Return whether badly hurt army units are pulled back out of fights
+    pub fn retreats(self) -> bool {
+        matches!(
+            self,
+            Difficulty::Normal | Difficulty::Hard | Difficulty::Insane
+        )
+    }
+
+    /// Return the multiplier applied to the resources a computer player gathers
+    pub fn income(self) -> f32 {
+        match self {
+            Difficulty::Easy | Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.25,
+            Difficulty::Insane => 1.5,
+        }
+    }
+}
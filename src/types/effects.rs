@@ -0,0 +1,35 @@
+use bracket_lib::prelude::{Point, RGB};
+
+/// Something that happened during a tick that is worth showing but doesn't affect the match
+#[derive(Copy, Clone, Debug)]
+pub enum Effect {
+    /// A unit or building took the damage at the point
+    Damage(Point, u32),
+    /// A unit with the glyph and color died at the point
+    Death(Point, char, RGB),
+}
+
+/// A resource collecting the Effects of the last tick, cleared at the start of the next so they
+/// only pile up while something is taking them to be shown
+#[derive(Clone, Debug, Default)]
+pub struct Effects {
+    effects: Vec<Effect>,
+}
+
+impl Effects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+    pub fn push(&mut self, effect: Effect) {
+        self.effects.push(effect);
+    }
+
+    /// Return every Effect of the last tick that hasn't been taken yet
+    pub fn take(&mut self) -> Vec<Effect> {
+        std::mem::take(&mut self.effects)
+    }
+}
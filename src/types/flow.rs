@@ -0,0 +1,52 @@
+use bracket_lib::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::Map;
+
+/// A map of movement costs towards a set of target points, computed once and shared by
+/// every cell in a large group order
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FlowField {
+    costs: Vec<f32>,
+}
+
+impl FlowField {
+    pub fn new(map: &Map, targets: &[Point]) -> Self {
+        let starts: Vec<usize> = targets.iter().filter_map(|p| map.idx(*p)).collect();
+        let dijkstra = DijkstraMap::new(
+            map.width() as usize,
+            map.height() as usize,
+            &starts,
+            map,
+            (map.width() * map.height()) as f32,
+        );
+
+        Self {
+            costs: dijkstra.map,
+        }
+    }
+
+    /// Return the cost of travelling from the given point to the nearest target
+    pub fn cost(&self, map: &Map, point: Point) -> f32 {
+        match map.idx(point) {
+            Some(idx) => self.costs[idx],
+            None => std::f32::MAX,
+        }
+    }
+
+    /// Return the neighbouring point with the lowest cost, if any are lower than the given point
+    pub fn next_step(&self, map: &Map, point: Point) -> Option<Point> {
+        let idx = map.idx(point)?;
+
+        map.get_available_exits(idx)
+            .iter()
+            .filter(|(exit, _)| self.costs[*exit] < self.costs[idx])
+            .min_by(|(a, _), (b, _)| {
+                self.costs[*a]
+                    .partial_cmp(&self.costs[*b])
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|(exit, _)| map.index_to_point2d(*exit))
+    }
+}
@@ -1,7 +1,281 @@
-#[derive(Copy, Clone, Debug, PartialEq)]
+use bracket_lib::prelude::{VirtualKeyCode, RGB};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{unit_def, Race, UnitDef};
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum UnitKind {
     Trooper,
     FleshSpider,
     Blademaster,
     Strider,
+    Engineer,
+    Drone,
+    Harvester,
+}
+
+impl UnitKind {
+    pub const ALL: [UnitKind; 7] = [
+        UnitKind::Trooper,
+        UnitKind::FleshSpider,
+        UnitKind::Blademaster,
+        UnitKind::Strider,
+        UnitKind::Engineer,
+        UnitKind::Drone,
+        UnitKind::Harvester,
+    ];
+
+    /// Return the unit's stats from the unit definitions
+    pub fn def(self) -> &'static UnitDef {
+        unit_def(self)
+    }
+
+    pub fn race(self) -> Race {
+        self.def().race
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            UnitKind::Trooper => "Trooper",
+            UnitKind::FleshSpider => "Flesh Spider",
+            UnitKind::Blademaster => "Blademaster",
+            UnitKind::Strider => "Strider",
+            UnitKind::Engineer => "Engineer",
+            UnitKind::Drone => "Drone",
+            UnitKind::Harvester => "Harvester",
+        }
+    }
+
+    pub fn symbol(self) -> char {
+        self.def().glyph
+    }
+
+    pub fn color(self) -> RGB {
+        let (r, g, b) = self.def().color;
+        RGB::from_u8(r, g, b)
+    }
+
+    /// Return the minerals and gas it takes to train the unit
+    pub fn cost(self) -> (u32, u32) {
+        match self {
+            UnitKind::Trooper => (60, 20),
+            UnitKind::FleshSpider => (25, 0),
+            UnitKind::Blademaster => (50, 0),
+            UnitKind::Strider => (75, 25),
+            UnitKind::Engineer | UnitKind::Drone | UnitKind::Harvester => (50, 0),
+        }
+    }
+
+    /// Return whether the unit gathers resources
+    pub fn is_worker(self) -> bool {
+        matches!(
+            self,
+            UnitKind::Engineer | UnitKind::Drone | UnitKind::Harvester
+        )
+    }
+
+    /// Return how much of its owner's supply the unit takes up
+    pub fn supply(self) -> i32 {
+        match self {
+            UnitKind::Trooper => 2,
+            UnitKind::FleshSpider
+            | UnitKind::Blademaster
+            | UnitKind::Strider
+            | UnitKind::Engineer
+            | UnitKind::Drone
+            | UnitKind::Harvester => 1,
+        }
+    }
+
+    /// Return the number of seconds it takes to train the unit
+    pub fn train_time(self) -> f32 {
+        match self {
+            UnitKind::Trooper => 9.0,
+            UnitKind::FleshSpider => 5.0,
+            UnitKind::Blademaster => 8.0,
+            UnitKind::Strider => 10.0,
+            UnitKind::Engineer | UnitKind::Drone | UnitKind::Harvester => 6.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BuildingKind {
+    Headquarters,
+    Barracks,
+    Generator,
+    Hive,
+    SpawningPit,
+    Nest,
+    Nexus,
+    Portal,
+    Obelisk,
+}
+
+impl BuildingKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            BuildingKind::Headquarters => "Headquarters",
+            BuildingKind::Barracks => "Barracks",
+            BuildingKind::Generator => "Generator",
+            BuildingKind::Hive => "Hive",
+            BuildingKind::SpawningPit => "Spawning Pit",
+            BuildingKind::Nest => "Nest",
+            BuildingKind::Nexus => "Nexus",
+            BuildingKind::Portal => "Portal",
+            BuildingKind::Obelisk => "Obelisk",
+        }
+    }
+
+    /// Return the key used to pick the building from the build menu
+    pub fn hotkey(self) -> VirtualKeyCode {
+        match self {
+            BuildingKind::Headquarters | BuildingKind::Hive => VirtualKeyCode::H,
+            BuildingKind::Barracks => VirtualKeyCode::B,
+            BuildingKind::Generator => VirtualKeyCode::G,
+            BuildingKind::SpawningPit => VirtualKeyCode::S,
+            BuildingKind::Nest | BuildingKind::Nexus => VirtualKeyCode::N,
+            BuildingKind::Portal => VirtualKeyCode::P,
+            BuildingKind::Obelisk => VirtualKeyCode::O,
+        }
+    }
+    /// Return the letter shown for the building in the build menu
+    pub fn letter(self) -> char {
+        self.name().chars().next().unwrap_or(' ')
+    }
+
+    /// Return the minerals and gas it takes to construct the building
+    pub fn cost(self) -> (u32, u32) {
+        match self {
+            BuildingKind::Headquarters | BuildingKind::Hive | BuildingKind::Nexus => (400, 0),
+            BuildingKind::Barracks | BuildingKind::SpawningPit => (150, 0),
+            BuildingKind::Portal => (150, 50),
+            BuildingKind::Generator | BuildingKind::Nest | BuildingKind::Obelisk => (100, 0),
+        }
+    }
+
+    /// Return whether workers can drop off resources at the building
+    pub fn is_depot(self) -> bool {
+        matches!(
+            self,
+            BuildingKind::Headquarters | BuildingKind::Hive | BuildingKind::Nexus
+        )
+    }
+
+    /// Return how much the building raises its owner's supply cap once constructed
+    pub fn supply(self) -> i32 {
+        match self {
+            BuildingKind::Headquarters | BuildingKind::Hive | BuildingKind::Nexus => 50,
+            BuildingKind::Generator | BuildingKind::Nest | BuildingKind::Obelisk => 20,
+            BuildingKind::Barracks | BuildingKind::SpawningPit | BuildingKind::Portal => 0,
+        }
+    }
+
+    /// Return the number of seconds it takes to construct the building
+    pub fn build_time(self) -> f32 {
+        match self {
+            BuildingKind::Headquarters | BuildingKind::Hive | BuildingKind::Nexus => 40.0,
+            BuildingKind::Barracks | BuildingKind::SpawningPit | BuildingKind::Portal => 25.0,
+            BuildingKind::Generator | BuildingKind::Nest | BuildingKind::Obelisk => 15.0,
+        }
+    }
+
+    pub fn race(self) -> Race {
+        match self {
+            BuildingKind::Headquarters | BuildingKind::Barracks | BuildingKind::Generator => {
+                Race::Bionic
+            }
+            BuildingKind::Hive | BuildingKind::SpawningPit | BuildingKind::Nest => Race::Bug,
+            BuildingKind::Nexus | BuildingKind::Portal | BuildingKind::Obelisk => Race::Alien,
+        }
+    }
+
+    pub fn hp(self) -> i32 {
+        match self {
+            BuildingKind::Headquarters | BuildingKind::Hive | BuildingKind::Nexus => 500,
+            BuildingKind::Barracks | BuildingKind::SpawningPit | BuildingKind::Portal => 250,
+            BuildingKind::Generator | BuildingKind::Nest | BuildingKind::Obelisk => 100,
+        }
+    }
+
+    /// Return the units the building is able to train
+    pub fn trains(self) -> &'static [UnitKind] {
+        match self {
+            BuildingKind::Headquarters => &[UnitKind::Engineer],
+            BuildingKind::Hive => &[UnitKind::Drone],
+            BuildingKind::Nexus => &[UnitKind::Harvester],
+            BuildingKind::Barracks => &[UnitKind::Blademaster, UnitKind::Strider],
+            BuildingKind::SpawningPit => &[UnitKind::FleshSpider],
+            BuildingKind::Portal => &[UnitKind::Trooper],
+            _ => &[],
+        }
+    }
+
+    /// Return how many tiles away the building can see
+    pub fn sight(self) -> u32 {
+        if self.is_depot() {
+            9
+        } else {
+            6
+        }
+    }
+
+    /// Return the rows of glyphs drawn over the building's footprint
+    pub fn art(self) -> &'static [&'static str] {
+        match self {
+            BuildingKind::Headquarters => &["[=]", "|H|", "[=]"],
+            BuildingKind::Barracks => &["/B\\", "|_|"],
+            BuildingKind::Generator => &["++", "++"],
+            BuildingKind::Hive => &["(@)", "@@@", "(@)"],
+            BuildingKind::SpawningPit => &["{}", "{}"],
+            BuildingKind::Nest => &["oo", "oo"],
+            BuildingKind::Nexus => &["<N>", "|#|", "<=>"],
+            BuildingKind::Portal => &["[]", "[]"],
+            BuildingKind::Obelisk => &["A", "|"],
+        }
+    }
+
+    /// Return the width and height of the building's footprint
+    pub fn size(self) -> (i32, i32) {
+        let art = self.art();
+        (art[0].len() as i32, art.len() as i32)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ResourceKind {
+    Minerals,
+    Gas,
+}
+
+impl ResourceKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ResourceKind::Minerals => "Minerals",
+            ResourceKind::Gas => "Gas",
+        }
+    }
+
+    pub fn symbol(self) -> char {
+        match self {
+            ResourceKind::Minerals => '%',
+            ResourceKind::Gas => '&',
+        }
+    }
+
+    pub fn color(self) -> RGB {
+        match self {
+            ResourceKind::Minerals => RGB::from_u8(0, 190, 255),
+            ResourceKind::Gas => RGB::from_u8(100, 220, 100),
+        }
+    }
+
+    /// Return the amount a single node of the resource starts with
+    pub fn amount(self) -> u32 {
+        match self {
+            ResourceKind::Minerals => 1500,
+            ResourceKind::Gas => 2500,
+        }
+    }
 }
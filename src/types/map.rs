@@ -1,11 +1,177 @@
+use std::collections::VecDeque;
+
+use bracket_lib::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::Tile;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Map {
     w: i32,
     h: i32,
+    tiles: Vec<Tile>,
+    blocked: Vec<bool>,
 }
 
 impl Map {
     pub fn new(w: i32, h: i32) -> Self {
-        Self { w, h }
+        Self {
+            w,
+            h,
+            tiles: vec![Tile::Ground; (w * h) as usize],
+            blocked: vec![false; (w * h) as usize],
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.w
+    }
+    pub fn height(&self) -> i32 {
+        self.h
+    }
+
+    /// Set every tile within the given Rect to the given Tile
+    pub fn fill(&mut self, rect: Rect, tile: Tile) {
+        rect.for_each(|point| self.set_tile(point, tile));
+    }
+
+    pub fn set_tile(&mut self, point: Point, tile: Tile) {
+        if let Some(idx) = self.idx(point) {
+            self.tiles[idx] = tile;
+        }
+    }
+
+    /// Mark every tile within the given Rect as blocked by a building
+    pub fn block(&mut self, rect: Rect) {
+        rect.for_each(|point| {
+            if let Some(idx) = self.idx(point) {
+                self.blocked[idx] = true;
+            }
+        });
+    }
+    /// Clear any buildings blocking the tiles within the given Rect
+    pub fn unblock(&mut self, rect: Rect) {
+        rect.for_each(|point| {
+            if let Some(idx) = self.idx(point) {
+                self.blocked[idx] = false;
+            }
+        });
+    }
+
+    /// Return the index of the tile at the given world point if it is on the map
+    pub fn idx(&self, point: Point) -> Option<usize> {
+        let x = point.x - self.lower_x();
+        let y = point.y - self.lower_y();
+        if x >= 0 && x < self.w && y >= 0 && y < self.h {
+            Some((y * self.w + x) as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn tile(&self, point: Point) -> Option<Tile> {
+        self.idx(point).map(|idx| self.tiles[idx])
+    }
+
+    /// Return whether the given world point is on the map and can be walked across
+    pub fn is_walkable(&self, point: Point) -> bool {
+        match self.idx(point) {
+            Some(idx) => self.tiles[idx].walkable() && !self.blocked[idx],
+            None => false,
+        }
+    }
+
+    /// Return whether every tile within the given Rect can be walked across
+    pub fn is_clear(&self, rect: Rect) -> bool {
+        let mut clear = true;
+        rect.for_each(|point| {
+            if !self.is_walkable(point) {
+                clear = false;
+            }
+        });
+        clear
+    }
+
+    /// Return the closest walkable point to the given point, searching outwards up to 10 tiles
+    pub fn nearest_walkable(&self, point: Point) -> Option<Point> {
+        let mut nearest: Option<(i32, Point)> = None;
+        for r in 0..10 {
+            // a tile in this ring is at least r away, so nothing further out can be closer
+            if nearest.map_or(false, |(d, _)| d <= r * r) {
+                break;
+            }
+            for y in point.y - r..=point.y + r {
+                for x in point.x - r..=point.x + r {
+                    let (dx, dy) = (x - point.x, y - point.y);
+                    if dx.abs() != r && dy.abs() != r {
+                        continue;
+                    }
+                    let d = dx * dx + dy * dy;
+                    if nearest.map_or(true, |(best, _)| d < best)
+                        && self.is_walkable(Point::new(x, y))
+                    {
+                        nearest = Some((d, Point::new(x, y)));
+                    }
+                }
+            }
+        }
+        nearest.map(|(_, point)| point)
+    }
+
+    /// Return up to n walkable points reachable from the given point, nearest first
+    pub fn spread(&self, center: Point, n: usize) -> Vec<Point> {
+        let mut points = Vec::with_capacity(n);
+        let start = match self.nearest_walkable(center).and_then(|p| self.idx(p)) {
+            Some(start) => start,
+            None => return points,
+        };
+
+        let mut visited = vec![false; self.tiles.len()];
+        let mut queue = VecDeque::new();
+        visited[start] = true;
+        queue.push_back(start);
+
+        while let Some(idx) = queue.pop_front() {
+            if points.len() >= n {
+                break;
+            }
+            points.push(self.index_to_point2d(idx));
+
+            for (exit, _) in self.get_available_exits(idx).iter() {
+                if !visited[*exit] {
+                    visited[*exit] = true;
+                    queue.push_back(*exit);
+                }
+            }
+        }
+
+        points
+    }
+
+    /// Find a path between two points with A*, returning the waypoints in reverse order
+    /// so that the next waypoint is always the last element
+    pub fn path(&self, start: Point, end: Point) -> Vec<Point> {
+        let end = match self.nearest_walkable(end) {
+            Some(end) => end,
+            None => return Vec::new(),
+        };
+        let (start, end) = match (self.idx(start), self.idx(end)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Vec::new(),
+        };
+
+        let path = a_star_search(start, end, self);
+        if path.success {
+            path.steps
+                .iter()
+                .skip(1)
+                .rev()
+                .map(|idx| self.index_to_point2d(*idx))
+                .collect()
+        } else {
+            Vec::new()
+        }
     }
 
     pub fn lower_x(&self) -> i32 {
@@ -21,3 +187,90 @@ impl Map {
         self.h / 2
     }
 }
+
+impl BaseMap for Map {
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.tiles.get(idx).map_or(true, |tile| tile.opaque())
+    }
+
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        let mut exits = SmallVec::new();
+        let point = self.index_to_point2d(idx);
+
+        for (dx, dy) in [
+            (0, -1),
+            (1, 0),
+            (0, 1),
+            (-1, 0),
+            (1, -1),
+            (1, 1),
+            (-1, 1),
+            (-1, -1),
+        ]
+        .iter()
+        {
+            let dest = Point::new(point.x + dx, point.y + dy);
+            if !self.is_walkable(dest) {
+                continue;
+            }
+            if *dx != 0 && *dy != 0 {
+                // don't cut corners around impassable tiles
+                if self.is_walkable(Point::new(point.x + dx, point.y))
+                    && self.is_walkable(Point::new(point.x, point.y + dy))
+                {
+                    exits.push((self.point2d_to_index(dest), 1.45));
+                }
+            } else {
+                exits.push((self.point2d_to_index(dest), 1.0));
+            }
+        }
+
+        exits
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        DistanceAlg::Pythagoras.distance2d(self.index_to_point2d(idx1), self.index_to_point2d(idx2))
+    }
+}
+
+impl Algorithm2D for Map {
+    fn dimensions(&self) -> Point {
+        Point::new(self.w, self.h)
+    }
+
+    fn in_bounds(&self, pos: Point) -> bool {
+        self.idx(pos).is_some()
+    }
+
+    fn point2d_to_index(&self, pt: Point) -> usize {
+        ((pt.y - self.lower_y()) * self.w + pt.x - self.lower_x()) as usize
+    }
+
+    fn index_to_point2d(&self, idx: usize) -> Point {
+        Point::new(
+            idx as i32 % self.w + self.lower_x(),
+            idx as i32 / self.w + self.lower_y(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_walkable_is_the_closest_open_tile() {
+        let mut map = Map::new(20, 20);
+        map.fill(Rect::with_size(-2, -2, 5, 5), Tile::Rock);
+
+        assert_eq!(
+            map.nearest_walkable(Point::new(5, 5)),
+            Some(Point::new(5, 5))
+        );
+        // the open tile straight across the rock is nearer than the corner found first
+        assert_eq!(
+            map.nearest_walkable(Point::new(1, 0)),
+            Some(Point::new(3, 0))
+        );
+    }
+}
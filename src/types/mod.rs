@@ -1,15 +1,45 @@
+mod button;
+mod command;
+mod condition;
 mod ctrlgroups;
+mod difficulty;
 mod direction;
+mod effects;
+mod flow;
 mod kind;
 mod map;
 mod mode;
 mod mouse;
+mod order;
+mod outcome;
+mod players;
 mod race;
+mod rng;
+mod stats;
+mod tile;
+mod time;
+mod units;
+mod vision;
 
+pub use button::Button;
+pub use command::Command;
+pub use condition::Condition;
 pub use ctrlgroups::CtrlGroups;
+pub use difficulty::Difficulty;
 pub use direction::Direction;
-pub use kind::UnitKind;
+pub use effects::{Effect, Effects};
+pub use flow::FlowField;
+pub use kind::{BuildingKind, ResourceKind, UnitKind};
 pub use map::Map;
 pub use mode::Mode;
 pub use mouse::Mouse;
+pub use order::{Order, Orders};
+pub use outcome::Outcome;
+pub use players::{PlayerInfo, Players, MAX_SUPPLY};
 pub use race::Race;
+pub use rng::Rng;
+pub use stats::{PlayerStats, Stats};
+pub use tile::Tile;
+pub use time::Time;
+pub use units::{load_unit_defs, unit_def, UnitDef, UnitDefs, UnitDefsError};
+pub use vision::Vision;
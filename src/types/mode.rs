@@ -1,4 +1,6 @@
-#[derive(Copy, Clone, Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Mode {
     Select,
     Move,
@@ -8,3 +10,15 @@ pub enum Mode {
     Ctrl,
     Add,
 }
+
+impl Mode {
+    /// Return what a unit in the Mode is doing, as shown in the selection panel
+    pub fn status(self) -> &'static str {
+        match self {
+            Mode::Move => "Moving",
+            Mode::Attack => "Attack-moving",
+            Mode::Hold => "Holding position",
+            _ => "Idle",
+        }
+    }
+}
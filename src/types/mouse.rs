@@ -6,6 +6,8 @@ pub struct Mouse {
     pub point: Point,
     pub click: Option<(usize, bool)>,
     pub pressed: (usize, bool, bool),
+    /// Where the mouse was when a button was last pressed, whatever the mode
+    pub down: Point,
     cursor: String,
     pub selection: Rect,
 }
@@ -16,6 +18,7 @@ impl Mouse {
             point: Point::new(0, 0),
             click: None,
             pressed: (0, false, false),
+            down: Point::new(0, 0),
             cursor: String::from("<"),
             selection: Rect::default(),
         }
@@ -0,0 +1,58 @@
+use bracket_lib::prelude::Point;
+
+use legion::Entity;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{Player, Rally},
+    simulation::save::{entity, entity_list},
+    types::{BuildingKind, Mode, UnitKind},
+};
+
+/// An order given to a player's entities, issued the same way by local input and the computer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Order {
+    /// Move, or attack-move, the units to a point
+    Move(#[serde(with = "entity_list")] Vec<Entity>, Point, Mode),
+    /// Attack a hostile entity ahead of anything else in range, following it until it is gone
+    Attack(
+        #[serde(with = "entity_list")] Vec<Entity>,
+        #[serde(with = "entity")] Entity,
+    ),
+    Stop(#[serde(with = "entity_list")] Vec<Entity>),
+    Hold(#[serde(with = "entity_list")] Vec<Entity>),
+    /// Send the workers to gather from a resource node
+    Gather(
+        #[serde(with = "entity_list")] Vec<Entity>,
+        #[serde(with = "entity")] Entity,
+    ),
+    /// Place a construction site with its top left corner at the point
+    Build(BuildingKind, Point),
+    /// Queue a unit in a production building
+    Train(#[serde(with = "entity")] Entity, UnitKind),
+    /// Cancel the last unit queued in a production building
+    Cancel(#[serde(with = "entity")] Entity),
+    /// Set the rally of the production buildings
+    Rally(#[serde(with = "entity_list")] Vec<Entity>, Rally),
+}
+
+/// A resource holding the orders to be carried out on the next tick and who issued them
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Orders {
+    queue: Vec<(Player, Order)>,
+}
+
+impl Orders {
+    pub fn new() -> Self {
+        Self { queue: Vec::new() }
+    }
+
+    pub fn push(&mut self, player: Player, order: Order) {
+        self.queue.push((player, order));
+    }
+    /// Remove and return every waiting order in the order it was issued
+    pub fn drain(&mut self) -> Vec<(Player, Order)> {
+        self.queue.drain(..).collect()
+    }
+}
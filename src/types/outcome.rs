@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::Condition;
+
+/// A resource tracking the match's victory conditions and, once one is met, how it ended
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Outcome {
+    conditions: Vec<Condition>,
+    /// The team holding each HoldPoint condition's area and for how many seconds
+    held: Vec<Option<(usize, f32)>>,
+    /// The winning teams and the tick the match ended on, where no winners is a draw
+    result: Option<(Vec<usize>, u64)>,
+}
+
+impl Outcome {
+    pub fn new(conditions: Vec<Condition>) -> Self {
+        Self {
+            held: vec![None; conditions.len()],
+            conditions,
+            result: None,
+        }
+    }
+
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    /// Count the seconds the team has held the area of the condition, or clear the count if no
+    /// team alone holds it, returning the total
+    pub fn hold(&mut self, condition: usize, team: Option<usize>, dt: f32) -> f32 {
+        let held = match (self.held[condition], team) {
+            (Some((holder, seconds)), Some(team)) if holder == team => Some((team, seconds + dt)),
+            (_, Some(team)) => Some((team, dt)),
+            (_, None) => None,
+        };
+        self.held[condition] = held;
+        held.map_or(0.0, |(_, seconds)| seconds)
+    }
+    /// Return the team holding the area of the condition and for how many seconds
+    pub fn held(&self, condition: usize) -> Option<(usize, f32)> {
+        self.held.get(condition).copied().flatten()
+    }
+
+    /// End the match on the tick with the teams as the winners
+    pub fn finish(&mut self, winners: Vec<usize>, tick: u64) {
+        if self.result.is_none() {
+            self.result = Some((winners, tick));
+        }
+    }
+    pub fn is_finished(&self) -> bool {
+        self.result.is_some()
+    }
+    /// Return the winning teams once the match has ended, which are none for a draw
+    pub fn winners(&self) -> Option<&[usize]> {
+        self.result.as_ref().map(|(winners, _)| winners.as_slice())
+    }
+    /// Return the tick the match ended on
+    pub fn end(&self) -> Option<u64> {
+        self.result.as_ref().map(|(_, tick)| *tick)
+    }
+}
@@ -0,0 +1,170 @@
+use bracket_lib::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::Player,
+    types::{Race, ResourceKind},
+};
+
+/// The highest a player's supply cap can be raised
+pub const MAX_SUPPLY: i32 = 200;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayerInfo {
+    name: String,
+    race: Race,
+    team: usize,
+    color: RGB,
+    minerals: u32,
+    gas: u32,
+    income: f32,
+    supply: (i32, i32),
+}
+
+impl PlayerInfo {
+    pub fn new(name: &str, race: Race, team: usize, color: RGB) -> Self {
+        Self {
+            name: name.to_string(),
+            race,
+            team,
+            color,
+            minerals: 0,
+            gas: 0,
+            income: 1.0,
+            supply: (0, 0),
+        }
+    }
+    pub fn with_resources(mut self, minerals: u32, gas: u32) -> Self {
+        self.minerals = minerals;
+        self.gas = gas;
+        self
+    }
+
+    /// Set the multiplier applied to every resource the player gathers
+    pub fn set_income(&mut self, x: f32) {
+        self.income = x;
+    }
+
+    /// Add the given amount of a resource to the player's stockpile, scaled by their income
+    pub fn earn(&mut self, kind: ResourceKind, x: u32) {
+        let x = (x as f32 * self.income).round() as u32;
+        match kind {
+            ResourceKind::Minerals => self.minerals += x,
+            ResourceKind::Gas => self.gas += x,
+        }
+    }
+    /// Return whether the player has enough minerals and gas for the given cost
+    pub fn can_afford(&self, cost: (u32, u32)) -> bool {
+        self.minerals >= cost.0 && self.gas >= cost.1
+    }
+    /// Take the given minerals and gas if the player can afford them
+    pub fn spend(&mut self, cost: (u32, u32)) -> bool {
+        if self.can_afford(cost) {
+            self.minerals -= cost.0;
+            self.gas -= cost.1;
+            true
+        } else {
+            false
+        }
+    }
+    /// Give back the minerals and gas of a cancelled order
+    pub fn refund(&mut self, cost: (u32, u32)) {
+        self.minerals += cost.0;
+        self.gas += cost.1;
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn race(&self) -> Race {
+        self.race
+    }
+    pub fn team(&self) -> usize {
+        self.team
+    }
+    pub fn color(&self) -> RGB {
+        self.color
+    }
+    pub fn minerals(&self) -> u32 {
+        self.minerals
+    }
+    pub fn gas(&self) -> u32 {
+        self.gas
+    }
+    /// Return the used supply and supply cap of the player
+    pub fn supply(&self) -> (i32, i32) {
+        self.supply
+    }
+    /// Return whether the player has room in their supply for the given amount
+    pub fn has_supply(&self, x: i32) -> bool {
+        self.supply.0 + x <= self.supply.1
+    }
+}
+
+/// Every player in the match, their team alliances, and which one is controlled locally
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Players {
+    players: Vec<PlayerInfo>,
+    local: usize,
+}
+
+impl Players {
+    pub fn new(local: usize) -> Self {
+        Self {
+            players: Vec::new(),
+            local,
+        }
+    }
+    pub fn with_player(mut self, info: PlayerInfo) -> Self {
+        self.players.push(info);
+        self
+    }
+
+    /// Return whether the two players are on different teams
+    pub fn hostile(&self, a: Player, b: Player) -> bool {
+        match (self.get(a), self.get(b)) {
+            (Some(a), Some(b)) => a.team() != b.team(),
+            _ => false,
+        }
+    }
+
+    pub fn get(&self, player: Player) -> Option<&PlayerInfo> {
+        self.players.get(player.id())
+    }
+    pub fn get_mut(&mut self, player: Player) -> Option<&mut PlayerInfo> {
+        self.players.get_mut(player.id())
+    }
+    /// Clear the supply of every player so it can be recounted
+    pub fn reset_supply(&mut self) {
+        for info in self.players.iter_mut() {
+            info.supply = (0, 0);
+        }
+    }
+    /// Add to the used supply and supply cap of the given player
+    pub fn add_supply(&mut self, player: Player, used: i32, cap: i32) {
+        if let Some(info) = self.get_mut(player) {
+            info.supply.0 += used;
+            info.supply.1 = (info.supply.1 + cap).min(MAX_SUPPLY);
+        }
+    }
+
+    /// Return every player in the match
+    pub fn all(&self) -> Vec<Player> {
+        (0..self.players.len()).map(Player::new).collect()
+    }
+    /// Return every player other than the local one
+    pub fn computers(&self) -> Vec<Player> {
+        (0..self.players.len())
+            .filter(|id| *id != self.local)
+            .map(Player::new)
+            .collect()
+    }
+
+    pub fn local(&self) -> Player {
+        Player::new(self.local)
+    }
+    pub fn is_local(&self, player: Player) -> bool {
+        player.id() == self.local
+    }
+}
@@ -1,7 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::{BuildingKind, UnitKind};
+
 /// An enum representing the different Races a Unit can be
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Race {
     Alien,
     Bug,
     Bionic,
 }
+
+impl Race {
+    pub fn name(self) -> &'static str {
+        match self {
+            Race::Alien => "Alien",
+            Race::Bug => "Bug",
+            Race::Bionic => "Bionic",
+        }
+    }
+
+    /// Return the buildings the Race is able to construct
+    pub fn buildings(self) -> &'static [BuildingKind] {
+        match self {
+            Race::Alien => &[
+                BuildingKind::Nexus,
+                BuildingKind::Portal,
+                BuildingKind::Obelisk,
+            ],
+            Race::Bug => &[
+                BuildingKind::Hive,
+                BuildingKind::SpawningPit,
+                BuildingKind::Nest,
+            ],
+            Race::Bionic => &[
+                BuildingKind::Headquarters,
+                BuildingKind::Barracks,
+                BuildingKind::Generator,
+            ],
+        }
+    }
+
+    /// Return the unit the Race gathers resources with
+    pub fn worker(self) -> UnitKind {
+        match self {
+            Race::Alien => UnitKind::Harvester,
+            Race::Bug => UnitKind::Drone,
+            Race::Bionic => UnitKind::Engineer,
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+/// A resource for the seeded random number generator behind every random choice in the
+/// simulation, so a match plays out the same given the same seed and orders. It is a SplitMix64
+/// generator so its state is a single number that can be written to a save.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Rng {
+    seed: u64,
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: seed }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Return a random number from low up to but not including high
+    pub fn range(&mut self, low: i32, high: i32) -> i32 {
+        if high <= low {
+            return low;
+        }
+        let span = (high as i64 - low as i64) as u64;
+        (low as i64 + (self.next() % span) as i64) as i32
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
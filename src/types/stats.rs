@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::components::Player;
+
+/// What one player has done over the course of a match
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub produced: u32,
+    pub lost: u32,
+    pub damage_dealt: u32,
+}
+
+/// A resource tallying the units each player produced and lost and the damage they dealt
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Stats {
+    players: Vec<PlayerStats>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn entry(&mut self, player: Player) -> &mut PlayerStats {
+        if self.players.len() <= player.id() {
+            self.players.resize(player.id() + 1, PlayerStats::default());
+        }
+        &mut self.players[player.id()]
+    }
+
+    pub fn add_produced(&mut self, player: Player) {
+        self.entry(player).produced += 1;
+    }
+    pub fn add_lost(&mut self, player: Player) {
+        self.entry(player).lost += 1;
+    }
+    pub fn add_damage(&mut self, player: Player, x: u32) {
+        self.entry(player).damage_dealt += x;
+    }
+
+    pub fn get(&self, player: Player) -> PlayerStats {
+        self.players.get(player.id()).copied().unwrap_or_default()
+    }
+}
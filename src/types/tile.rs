@@ -0,0 +1,51 @@
+use bracket_lib::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+/// An enum representing the different kinds of terrain a map tile can be
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Tile {
+    Ground,
+    Rock,
+    Water,
+    Cliff,
+    Ramp,
+}
+
+impl Tile {
+    pub fn symbol(self) -> char {
+        match self {
+            Tile::Ground => '.',
+            Tile::Rock => '#',
+            Tile::Water => '~',
+            Tile::Cliff => '^',
+            Tile::Ramp => '=',
+        }
+    }
+
+    pub fn color(self) -> RGB {
+        match self {
+            Tile::Ground => RGB::from_u8(100, 100, 100),
+            Tile::Rock => RGB::from_u8(140, 115, 90),
+            Tile::Water => RGB::from_u8(30, 70, 190),
+            Tile::Cliff => RGB::from_u8(170, 170, 170),
+            Tile::Ramp => RGB::from_u8(125, 100, 60),
+        }
+    }
+
+    /// Return whether the tile blocks line of sight
+    pub fn opaque(self) -> bool {
+        match self {
+            Tile::Rock | Tile::Cliff => true,
+            Tile::Ground | Tile::Water | Tile::Ramp => false,
+        }
+    }
+
+    /// Return whether units are able to move across the tile
+    pub fn walkable(self) -> bool {
+        match self {
+            Tile::Ground | Tile::Ramp => true,
+            Tile::Rock | Tile::Water | Tile::Cliff => false,
+        }
+    }
+}
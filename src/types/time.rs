@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// A resource holding the fixed number of seconds each tick of the schedule covers and how
+/// many ticks have run
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Time {
+    dt: f32,
+    tick: u64,
+}
+
+impl Time {
+    pub fn new(dt: f32) -> Self {
+        Self { dt, tick: 0 }
+    }
+
+    /// Count another tick of the schedule
+    pub fn advance(&mut self) {
+        self.tick += 1;
+    }
+
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+}
@@ -0,0 +1,287 @@
+use std::{error::Error, fmt, fs, io, path::Path, sync::OnceLock};
+
+use serde::Deserialize;
+
+use crate::{
+    components::MAX_ATTACK_RATE,
+    types::{Race, UnitKind},
+};
+
+/// The unit definitions built into the game, used when no others were loaded
+const BUILT_IN: &str = include_str!("../../data/units.ron");
+
+static DEFS: OnceLock<UnitDefs> = OnceLock::new();
+
+/// The stats of one kind of unit as read from the unit definitions file
+#[derive(Clone, Debug, Deserialize)]
+pub struct UnitDef {
+    pub kind: UnitKind,
+    pub race: Race,
+    pub glyph: char,
+    pub color: (u8, u8, u8),
+    pub hp: i32,
+    pub speed: f32,
+    pub damage: u32,
+    pub attack_rate: f32,
+    pub range: u32,
+    pub follow_dist: u32,
+    pub sight: u32,
+}
+
+impl UnitDef {
+    /// Return a description of what is wrong with the definition, if anything
+    fn problem(&self) -> Option<String> {
+        if self.glyph.is_whitespace() {
+            return Some("has a blank glyph".to_string());
+        }
+        if self.hp <= 0 {
+            return Some(format!("has {} hp, but needs more than 0", self.hp));
+        }
+        if !(self.speed > 0.0 && self.speed.is_finite()) {
+            return Some(format!(
+                "has a speed of {}, but needs more than 0",
+                self.speed
+            ));
+        }
+        if !(self.attack_rate > 0.0 && self.attack_rate <= MAX_ATTACK_RATE) {
+            return Some(format!(
+                "has an attack_rate of {}, but needs more than 0 and at most {}",
+                self.attack_rate, MAX_ATTACK_RATE
+            ));
+        }
+        if self.sight == 0 {
+            return Some("has a sight of 0, but needs more".to_string());
+        }
+        if self.follow_dist < self.range {
+            return Some(format!(
+                "has a follow_dist of {}, shorter than its range of {}",
+                self.follow_dist, self.range
+            ));
+        }
+        [Race::Alien, Race::Bug, Race::Bionic]
+            .iter()
+            .flat_map(|race| race.buildings())
+            .find(|building| building.trains().contains(&self.kind) && building.race() != self.race)
+            .map(|building| {
+                format!(
+                    "is trained by the {} so must be of its race, {:?}, not {:?}",
+                    building.name(),
+                    building.race(),
+                    self.race
+                )
+            })
+    }
+}
+
+/// Why the unit definitions could not be used
+#[derive(Debug)]
+pub enum UnitDefsError {
+    Io(io::Error),
+    /// The file could not be parsed, naming the unit whose entry is at fault if it can be told
+    Parse(Option<UnitKind>, ron::Error),
+    Missing(UnitKind),
+    Duplicate(UnitKind),
+    Invalid(UnitKind, String),
+    /// The definitions were already looked up, so others can no longer be loaded in their place
+    AlreadyLoaded,
+}
+
+impl fmt::Display for UnitDefsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitDefsError::Io(e) => write!(f, "the unit definitions could not be read: {}", e),
+            UnitDefsError::Parse(Some(kind), e) => {
+                write!(f, "the {} could not be parsed: {}", kind.name(), e)
+            }
+            UnitDefsError::Parse(None, e) => {
+                write!(f, "the unit definitions could not be parsed: {}", e)
+            }
+            UnitDefsError::Missing(kind) => write!(f, "the {} has no definition", kind.name()),
+            UnitDefsError::Duplicate(kind) => {
+                write!(f, "the {} is defined more than once", kind.name())
+            }
+            UnitDefsError::Invalid(kind, problem) => write!(f, "the {} {}", kind.name(), problem),
+            UnitDefsError::AlreadyLoaded => write!(
+                f,
+                "the unit definitions were already in use, so others could not be loaded"
+            ),
+        }
+    }
+}
+
+impl Error for UnitDefsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            UnitDefsError::Io(e) => Some(e),
+            UnitDefsError::Parse(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// The definition of every UnitKind
+#[derive(Clone, Debug)]
+pub struct UnitDefs {
+    defs: Vec<UnitDef>,
+}
+
+impl UnitDefs {
+    /// Parse and validate unit definitions, which must define every UnitKind exactly once
+    pub fn parse(s: &str) -> Result<Self, UnitDefsError> {
+        let defs: Vec<UnitDef> = ron::de::from_str(s).map_err(|e| {
+            let kind = entries(s)
+                .into_iter()
+                .find(|entry| ron::de::from_str::<UnitDef>(entry).is_err())
+                .and_then(kind_named);
+            UnitDefsError::Parse(kind, e)
+        })?;
+
+        for (i, def) in defs.iter().enumerate() {
+            if defs[..i].iter().any(|other| other.kind == def.kind) {
+                return Err(UnitDefsError::Duplicate(def.kind));
+            }
+            if let Some(problem) = def.problem() {
+                return Err(UnitDefsError::Invalid(def.kind, problem));
+            }
+        }
+        if let Some(kind) = UnitKind::ALL
+            .iter()
+            .find(|kind| defs.iter().all(|def| def.kind != **kind))
+        {
+            return Err(UnitDefsError::Missing(*kind));
+        }
+
+        Ok(Self { defs })
+    }
+
+    pub fn get(&self, kind: UnitKind) -> &UnitDef {
+        self.defs
+            .iter()
+            .find(|def| def.kind == kind)
+            .expect("every UnitKind is defined")
+    }
+}
+
+/// Split the text of a list of definitions into the text of each entry
+fn entries(s: &str) -> Vec<&str> {
+    let mut entries = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '/' if chars.peek().map(|(_, c)| *c) == Some('/') => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '\'' | '"' => {
+                while let Some((_, next)) = chars.next() {
+                    if next == '\\' {
+                        chars.next();
+                    } else if next == c {
+                        break;
+                    }
+                }
+            }
+            '(' | '[' | '{' => {
+                depth += 1;
+                if depth == 2 {
+                    start = i;
+                }
+            }
+            ')' | ']' | '}' => {
+                if depth == 2 {
+                    entries.push(&s[start..=i]);
+                }
+                depth -= 1;
+            }
+            _ => (),
+        }
+    }
+    entries
+}
+
+/// Return the UnitKind an entry defines, if its kind can be read
+fn kind_named(entry: &str) -> Option<UnitKind> {
+    let name: String = entry
+        .split("kind:")
+        .nth(1)?
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    ron::de::from_str(&name).ok()
+}
+
+/// Read the unit definitions used for the rest of the run from a file. They can only be loaded
+/// before the first unit is looked up.
+pub fn load_unit_defs<P: AsRef<Path>>(path: P) -> Result<(), UnitDefsError> {
+    let defs = UnitDefs::parse(&fs::read_to_string(path).map_err(UnitDefsError::Io)?)?;
+    DEFS.set(defs).map_err(|_| UnitDefsError::AlreadyLoaded)
+}
+
+/// Return the definition of the UnitKind, falling back on the built-in ones if none were loaded
+pub fn unit_def(kind: UnitKind) -> &'static UnitDef {
+    DEFS.get_or_init(|| UnitDefs::parse(BUILT_IN).expect("the built-in unit definitions are valid"))
+        .get(kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_definitions_are_valid() {
+        assert!(UnitDefs::parse(BUILT_IN).is_ok());
+        assert_eq!(entries(BUILT_IN).len(), UnitKind::ALL.len());
+    }
+
+    #[test]
+    fn duplicate_definitions_are_rejected() {
+        let s = BUILT_IN.replacen("kind: Harvester", "kind: Trooper", 1);
+        assert!(matches!(
+            UnitDefs::parse(&s),
+            Err(UnitDefsError::Duplicate(UnitKind::Trooper))
+        ));
+    }
+
+    #[test]
+    fn missing_definitions_are_rejected() {
+        let s = format!("[{}]", entries(BUILT_IN)[1..].join(","));
+        assert!(matches!(
+            UnitDefs::parse(&s),
+            Err(UnitDefsError::Missing(UnitKind::Trooper))
+        ));
+    }
+
+    #[test]
+    fn invalid_definitions_are_rejected() {
+        let s = BUILT_IN.replacen("hp: 20", "hp: 0", 1);
+        assert!(matches!(
+            UnitDefs::parse(&s),
+            Err(UnitDefsError::Invalid(UnitKind::Trooper, _))
+        ));
+    }
+
+    #[test]
+    fn parse_errors_name_the_unit() {
+        let s = BUILT_IN.replacen("speed: 12.5", "speed: fast", 1);
+        assert!(matches!(
+            UnitDefs::parse(&s),
+            Err(UnitDefsError::Parse(Some(UnitKind::Harvester), _))
+        ));
+    }
+
+    #[test]
+    fn definitions_cannot_be_loaded_once_in_use() {
+        unit_def(UnitKind::Trooper);
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/data/units.ron");
+        assert!(matches!(
+            load_unit_defs(path),
+            Err(UnitDefsError::AlreadyLoaded)
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+use bracket_lib::prelude::{Point, Rect};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::Map;
+
+/// A resource holding the tiles each team has ever seen and the tiles it can see right now
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Vision {
+    w: i32,
+    h: i32,
+    explored: Vec<Vec<bool>>,
+    visible: Vec<Vec<bool>>,
+}
+
+impl Vision {
+    pub fn new(map: &Map) -> Self {
+        Self {
+            w: map.width(),
+            h: map.height(),
+            explored: Vec::new(),
+            visible: Vec::new(),
+        }
+    }
+
+    /// Return the index of the tile at the given world point, laid out the same as the Map
+    fn idx(&self, point: Point) -> Option<usize> {
+        let x = point.x + self.w / 2;
+        let y = point.y + self.h / 2;
+        if x >= 0 && x < self.w && y >= 0 && y < self.h {
+            Some((y * self.w + x) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Forget what every team can see so it can be worked out again
+    pub fn clear(&mut self) {
+        for visible in self.visible.iter_mut() {
+            visible.iter_mut().for_each(|tile| *tile = false);
+        }
+    }
+    /// Mark the point as seen by the team now and explored from here on
+    pub fn reveal(&mut self, team: usize, point: Point) {
+        let idx = match self.idx(point) {
+            Some(idx) => idx,
+            None => return,
+        };
+        let size = (self.w * self.h) as usize;
+        if self.visible.len() <= team {
+            self.visible.resize(team + 1, vec![false; size]);
+            self.explored.resize(team + 1, vec![false; size]);
+        }
+        self.visible[team][idx] = true;
+        self.explored[team][idx] = true;
+    }
+
+    pub fn is_visible(&self, team: usize, point: Point) -> bool {
+        match (self.visible.get(team), self.idx(point)) {
+            (Some(visible), Some(idx)) => visible[idx],
+            _ => false,
+        }
+    }
+    pub fn is_explored(&self, team: usize, point: Point) -> bool {
+        match (self.explored.get(team), self.idx(point)) {
+            (Some(explored), Some(idx)) => explored[idx],
+            _ => false,
+        }
+    }
+    /// Return whether the team can see any tile within the Rect
+    pub fn any_visible(&self, team: usize, rect: Rect) -> bool {
+        let mut visible = false;
+        rect.for_each(|point| {
+            if self.is_visible(team, point) {
+                visible = true;
+            }
+        });
+        visible
+    }
+}